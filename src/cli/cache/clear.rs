@@ -1,13 +1,22 @@
-use crate::{Result, env};
+use crate::{Result, config::Config, env};
 
 #[derive(Debug, clap::Args)]
-pub struct Clear {}
+pub struct Clear {
+    /// Only clear the parsed config files
+    #[clap(long)]
+    config: bool,
+}
 
 impl Clear {
     pub async fn run(&self) -> Result<()> {
-        if env::HK_CACHE_DIR.exists() {
-            xx::file::remove_dir_all(&*env::HK_CACHE_DIR)?;
-            xx::file::mkdirp(&*env::HK_CACHE_DIR)?;
+        let dir = if self.config {
+            Config::cache_dir()
+        } else {
+            env::HK_CACHE_DIR.clone()
+        };
+        if dir.exists() {
+            xx::file::remove_dir_all(&dir)?;
+            xx::file::mkdirp(&dir)?;
         }
         Ok(())
     }
@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use crate::{Result, env, ui::style};
+use xx::file::display_path;
+
+#[derive(Debug, clap::Args)]
+pub struct Info {}
+
+impl Info {
+    pub async fn run(&self) -> Result<()> {
+        println!("cache dir: {}", display_path(&*env::HK_CACHE_DIR));
+        println!("size: {}", human_size(dir_size(&env::HK_CACHE_DIR)));
+
+        let configs = super::config_caches()?;
+        println!("configs: {}", configs.len());
+        for (file, cached) in &configs {
+            let size = human_size(file.metadata().map(|m| m.len()).unwrap_or_default());
+            match cached {
+                Some(cached) if cached.path.exists() => {
+                    println!("  {} ({size})", display_path(&cached.path))
+                }
+                Some(cached) => println!(
+                    "  {} ({size}) {}",
+                    display_path(&cached.path),
+                    style::ndim("missing")
+                ),
+                None => println!(
+                    "  {} ({size}) {}",
+                    display_path(file),
+                    style::ndim("unreadable")
+                ),
+            }
+        }
+
+        let steps = super::result_caches()?;
+        let files: usize = steps.iter().map(|(_, entries)| entries.len()).sum();
+        println!("results: {} steps, {files} files", steps.len());
+        Ok(())
+    }
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            _ => entry.metadata().map(|m| m.len()).unwrap_or_default(),
+        })
+        .sum()
+}
+
+fn human_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KB", "MB", "GB"] {
+        if size < 1024.0 {
+            return match unit {
+                "B" => format!("{bytes} B"),
+                _ => format!("{size:.1} {unit}"),
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{size:.1} TB")
+}
@@ -1,10 +1,17 @@
-use crate::Result;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    Result,
+    config::{CachedConfig, Config},
+    result_cache::ResultCache,
+};
 
 mod clear;
+mod info;
+mod prune;
 
 /// Manage hk internal cache
 #[derive(Debug, clap::Args)]
-#[clap(hide = true)] // TODO: unhide if we actually use cache (which we probably will)
 pub struct Cache {
     #[clap(subcommand)]
     command: Commands,
@@ -14,12 +21,55 @@ pub struct Cache {
 enum Commands {
     /// Clear the cache directory
     Clear(clear::Clear),
+    /// Show what is in the cache directory and how much space it uses
+    Info(info::Info),
+    /// Remove old cache entries and config caches for projects that no longer exist
+    Prune(prune::Prune),
 }
 
 impl Cache {
     pub async fn run(self) -> Result<()> {
         match self.command {
             Commands::Clear(cmd) => cmd.run().await,
+            Commands::Info(cmd) => cmd.run().await,
+            Commands::Prune(cmd) => cmd.run().await,
         }
     }
 }
+
+/// Every cached config file along with its contents if it could be parsed
+fn config_caches() -> Result<Vec<(PathBuf, Option<CachedConfig>)>> {
+    Ok(files(&Config::cache_dir())?
+        .into_iter()
+        .map(|file| {
+            // caches written by older versions of hk won't parse
+            let cached = xx::file::read_to_string(&file)
+                .ok()
+                .and_then(|raw| serde_json::from_str(&raw).ok());
+            (file, cached)
+        })
+        .collect())
+}
+
+/// Every step's result cache directory along with its entries
+fn result_caches() -> Result<Vec<(PathBuf, Vec<PathBuf>)>> {
+    files(&ResultCache::dir())?
+        .into_iter()
+        .filter(|dir| dir.is_dir())
+        .map(|dir| {
+            let entries = files(&dir)?;
+            Ok((dir, entries))
+        })
+        .collect()
+}
+
+fn files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut files = std::fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    files.sort();
+    Ok(files)
+}
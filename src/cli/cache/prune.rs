@@ -0,0 +1,83 @@
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use crate::Result;
+use xx::file::display_path;
+
+#[derive(Debug, clap::Args)]
+pub struct Prune {
+    /// Remove entries which haven't been used in this many days
+    #[clap(long, default_value_t = 30)]
+    days: u64,
+    /// Only show what would be removed
+    #[clap(long)]
+    dry_run: bool,
+}
+
+impl Prune {
+    pub async fn run(&self) -> Result<()> {
+        let max_age = Duration::from_secs(self.days * 24 * 60 * 60);
+        let verb = if self.dry_run {
+            "would remove"
+        } else {
+            "removing"
+        };
+        let mut removed = 0;
+        for (file, cached) in super::config_caches()? {
+            let reason = match &cached {
+                None => Some("unreadable".to_string()),
+                Some(cached) if !cached.path.exists() => {
+                    Some(format!("{} no longer exists", display_path(&cached.path)))
+                }
+                Some(_) if is_older_than(&file, max_age) => Some("old".to_string()),
+                Some(_) => None,
+            };
+            if let Some(reason) = reason {
+                info!("{verb} {} ({reason})", display_path(&file));
+                self.remove(&file)?;
+                removed += 1;
+            }
+        }
+        for (dir, entries) in super::result_caches()? {
+            let mut kept = entries.len();
+            for entry in entries {
+                if is_older_than(&entry, max_age) {
+                    debug!("{verb} {}", display_path(&entry));
+                    self.remove(&entry)?;
+                    removed += 1;
+                    kept -= 1;
+                }
+            }
+            if kept == 0 && !self.dry_run {
+                xx::file::remove_dir_all(&dir)?;
+            }
+        }
+        if self.dry_run {
+            info!("would remove {removed} cache entries");
+        } else {
+            info!("removed {removed} cache entries");
+        }
+        Ok(())
+    }
+
+    fn remove(&self, file: &Path) -> Result<()> {
+        if !self.dry_run {
+            xx::file::remove_file(file)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_older_than(path: &Path, age: Duration) -> bool {
+    path.metadata()
+        .and_then(|m| m.modified())
+        .map(|modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or_default()
+                > age
+        })
+        .unwrap_or(true)
+}
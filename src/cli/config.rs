@@ -1,15 +1,29 @@
-use crate::{Result, config::Config as HKConfig};
+use crate::{Result, config::Config as HKConfig, settings::Settings};
 
 /// Generate a default hk.toml configuration file
 #[derive(Debug, clap::Args)]
 #[clap(visible_alias = "cfg")]
-pub struct Config {}
+pub struct Config {
+    /// Print the effective settings instead of the config
+    #[clap(long)]
+    settings: bool,
+}
 
 impl Config {
     pub async fn run(&self) -> Result<()> {
         warn!("this output is almost certain to change in a future version");
         let cfg = HKConfig::get()?;
+        if self.settings {
+            print!("{}", toml::to_string(Settings::get())?);
+            return Ok(());
+        }
         println!("{cfg}");
+        if !cfg.sources.is_empty() {
+            println!("# sources");
+            for (key, path) in &cfg.sources {
+                println!("# {key}: {}", xx::file::display_path(path));
+            }
+        }
         Ok(())
     }
 }
@@ -13,7 +13,9 @@ mod config;
 mod fix;
 mod init;
 mod install;
+mod profiles;
 mod run;
+mod stash;
 mod uninstall;
 mod usage;
 mod validate;
@@ -59,7 +61,9 @@ enum Commands {
     Fix(Box<fix::Fix>),
     Init(Box<init::Init>),
     Install(Box<install::Install>),
+    Profiles(Box<profiles::Profiles>),
     Run(Box<run::Run>),
+    Stash(Box<stash::Stash>),
     Usage(Box<usage::Usage>),
     Uninstall(Box<uninstall::Uninstall>),
     Validate(Box<validate::Validate>),
@@ -107,7 +111,9 @@ pub async fn run() -> Result<()> {
         Commands::Fix(cmd) => cmd.hook.run("fix").await,
         Commands::Init(cmd) => cmd.run().await,
         Commands::Install(cmd) => cmd.run().await,
+        Commands::Profiles(cmd) => cmd.run().await,
         Commands::Run(cmd) => cmd.run().await,
+        Commands::Stash(cmd) => cmd.run().await,
         Commands::Uninstall(cmd) => cmd.run().await,
         Commands::Usage(cmd) => cmd.run().await,
         Commands::Validate(cmd) => cmd.run().await,
@@ -0,0 +1,33 @@
+use crate::{Result, config::Config, settings::Settings, ui::style};
+
+/// Lists the profiles declared in the config or used by steps
+#[derive(Debug, clap::Args)]
+pub struct Profiles {}
+
+impl Profiles {
+    pub async fn run(&self) -> Result<()> {
+        let config = Config::get()?;
+        let settings = Settings::get();
+        let known = config.known_profiles();
+        let width = known.iter().map(|p| p.len()).max().unwrap_or_default();
+        for name in &known {
+            let status = if settings.enabled_profiles.contains(name) {
+                "enabled ".to_string()
+            } else {
+                style::ndim("disabled").to_string()
+            };
+            let mut line = format!("{name:width$}  {status}");
+            if let Some(profile) = config.profiles.get(name) {
+                if let Some(description) = &profile.description {
+                    line.push_str(&format!("  {description}"));
+                }
+                if !profile.implies.is_empty() {
+                    let implies = format!("(implies {})", profile.implies.join(", "));
+                    line.push_str(&format!("  {}", style::ndim(implies)));
+                }
+            }
+            println!("{}", line.trim_end());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Local};
+
+use itertools::Itertools;
+
+use crate::{Result, git::StashMethod};
+
+#[derive(Debug, clap::Args)]
+pub struct List {}
+
+impl List {
+    pub async fn run(&self) -> Result<()> {
+        let entries = super::entries()?;
+        if entries.is_empty() {
+            info!("no stashed changes");
+        }
+        for entry in entries {
+            let created = DateTime::<Local>::from(entry.created).format("%Y-%m-%d %H:%M:%S");
+            let location = match (&entry.patch_file, &entry.commit) {
+                (Some(patch_file), _) => xx::file::display_path(patch_file),
+                (None, Some(commit)) => commit.clone(),
+                // only intent-to-add files were removed from the index
+                (None, None) if entry.method == StashMethod::None => format!(
+                    "intent-to-add: {}",
+                    entry.intent_to_add.iter().map(|f| f.display()).join(", ")
+                ),
+                (None, None) => "unknown stash commit".to_string(),
+            };
+            let running = if entry.is_running() {
+                format!(" (running, pid {})", entry.pid)
+            } else {
+                String::new()
+            };
+            println!(
+                "{}  {created}  {}  {location}{running}",
+                entry.id, entry.method
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{Result, git::Git, stash_journal::StashJournal};
+
+mod list;
+mod restore;
+
+/// Recover unstaged changes stashed by an hk run that was killed
+#[derive(Debug, clap::Args)]
+pub struct Stash {
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Commands {
+    /// List changes hk has stashed in this repository
+    List(list::List),
+    /// Re-apply changes hk stashed but never restored
+    Restore(restore::Restore),
+}
+
+impl Stash {
+    pub async fn run(self) -> Result<()> {
+        match self.command {
+            Commands::List(cmd) => cmd.run().await,
+            Commands::Restore(cmd) => cmd.run().await,
+        }
+    }
+}
+
+fn entries() -> Result<Vec<StashJournal>> {
+    let git = Git::new()?;
+    StashJournal::list(git.root())
+}
@@ -0,0 +1,35 @@
+use eyre::{bail, eyre};
+
+use crate::Result;
+
+#[derive(Debug, clap::Args)]
+pub struct Restore {
+    /// The stash to restore, defaults to the most recent one
+    id: Option<String>,
+}
+
+impl Restore {
+    pub async fn run(&self) -> Result<()> {
+        let entries = super::entries()?;
+        let entry = match &self.id {
+            Some(id) => entries
+                .into_iter()
+                .find(|e| &e.id == id)
+                .ok_or_else(|| eyre!("no stash named {id}, see `hk stash list`"))?,
+            None => entries
+                .into_iter()
+                .rfind(|e| !e.is_running())
+                .ok_or_else(|| eyre!("no stashed changes to restore"))?,
+        };
+        if entry.is_running() {
+            bail!(
+                "stash {} belongs to a running hk process (pid {})",
+                entry.id,
+                entry.pid
+            );
+        }
+        entry.restore()?;
+        info!("restored stash {}", entry.id);
+        Ok(())
+    }
+}
@@ -1,45 +1,221 @@
-use indexmap::IndexMap;
-use serde::{Deserialize, Serialize, de::DeserializeOwned};
-use std::path::{Path, PathBuf};
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-use crate::{Result, cache::CacheManagerBuilder, env, hash, hook::Hook, version};
+use crate::{
+    Result,
+    cache::CacheManagerBuilder,
+    env, hash,
+    hook::{Hook, StepOrGroup},
+    pkl::{self, Dependencies},
+    profile::Profile,
+    settings::{ConfigSettings, Settings},
+    version,
+};
 use eyre::{WrapErr, bail};
+use itertools::Itertools;
 
 impl Config {
+    /// Loads the user config, the project config and the project's local config, each one
+    /// overriding the ones before it
     pub fn get() -> Result<Self> {
         let default_path = env::HK_FILE
             .as_ref()
             .map(|s| s.as_str())
             .unwrap_or("hk.pkl");
+        let project = Self::project_path(default_path)?;
+        let local = project.as_deref().and_then(Self::local_path);
+        let layers = [Self::user_path(), project.clone(), local]
+            .into_iter()
+            .flatten()
+            .filter(|p| p.exists())
+            .collect::<Vec<_>>();
+        let mut config = Config::default();
+        for path in &layers {
+            config.merge(Self::load(path)?, path);
+        }
+        if project.is_none() {
+            debug!("No config file found, using default");
+        }
+        config.path = project.unwrap_or_else(|| PathBuf::from(default_path));
+        // after merging so a hook's defaults (e.g.: `timeout`) apply to steps from every file
+        for (name, hook) in config.hooks.iter_mut() {
+            hook.init(name);
+        }
+        for (key, value) in config.env.iter() {
+            unsafe { std::env::set_var(key, value) };
+        }
+        Settings::set_config(&config.settings, &config.profiles);
+        config.check_cli_profiles()?;
+        Ok(config)
+    }
+
+    fn project_path(default_path: &str) -> Result<Option<PathBuf>> {
         let paths = vec![default_path, "hk.toml", "hk.yaml", "hk.yml", "hk.json"];
         let mut cwd = std::env::current_dir()?;
         while cwd != Path::new("/") {
             for path in &paths {
                 let path = cwd.join(path);
                 if path.exists() {
-                    let hash_key = format!("{}.json", hash::hash_to_str(&path));
-                    let hash_key_path = env::HK_CACHE_DIR.join("configs").join(hash_key);
-                    return CacheManagerBuilder::new(hash_key_path)
-                        .with_fresh_file(path.to_path_buf())
-                        .build()
-                        .get_or_try_init(|| {
-                            Self::read(&path).wrap_err_with(|| {
-                                format!("Failed to read config file: {}", path.display())
-                            })
-                        })
-                        .cloned();
+                    return Ok(Some(path));
                 }
             }
             cwd = cwd.parent().map(PathBuf::from).unwrap_or_default();
         }
-        debug!("No config file found, using default");
-        let mut config = Config::default();
-        config.init(Path::new(default_path))?;
+        Ok(None)
+    }
+
+    /// e.g.: `~/.config/hk/config.pkl`
+    fn user_path() -> Option<PathBuf> {
+        [
+            "config.pkl",
+            "config.toml",
+            "config.yaml",
+            "config.yml",
+            "config.json",
+        ]
+        .into_iter()
+        .map(|f| env::HK_CONFIG_DIR.join(f))
+        .find(|p| p.exists())
+    }
+
+    /// An uncommitted file next to the project config, e.g.: `hk.local.pkl` for `hk.pkl`
+    fn local_path(project: &Path) -> Option<PathBuf> {
+        let stem = project.file_stem()?.to_str()?;
+        let ext = project.extension()?.to_str()?;
+        Some(project.with_file_name(format!("{stem}.local.{ext}")))
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let hash_key = format!("{}.json", hash::hash_to_str(&path));
+        let hash_key_path = Self::cache_dir().join(hash_key);
+        let cache = CacheManagerBuilder::new(hash_key_path)
+            .with_fresh_file(path.to_path_buf())
+            .build::<CachedConfig>();
+        let mut cached = cache.get_or_try_init(|| CachedConfig::read(path))?;
+        let reread;
+        if !cached.is_fresh() {
+            debug!("config dependencies changed, re-reading {}", path.display());
+            reread = CachedConfig::read(path)?;
+            if let Err(err) = cache.write(&reread) {
+                warn!("failed to write config cache: {err:#}");
+            }
+            cached = &reread;
+        }
+        let mut config = cached.config.clone();
+        config.path = cached.path.clone();
         Ok(config)
     }
 
-    fn read(path: &Path) -> Result<Self> {
+    /// Layers `other` over this config: hooks are merged, with steps replaced by name and
+    /// removed if they set `enabled = false`
+    fn merge(&mut self, other: Config, path: &Path) {
+        let mut source = |key: String| {
+            self.sources.insert(key, path.to_path_buf());
+        };
+        if other.min_hk_version.is_some() {
+            source("min_hk_version".to_string());
+            self.min_hk_version = other.min_hk_version;
+        }
+        for (key, value) in other.env {
+            source(format!("env.{key}"));
+            self.env.insert(key, value);
+        }
+        for (name, profile) in other.profiles {
+            source(format!("profiles.{name}"));
+            self.profiles.insert(name, profile);
+        }
+        for setting in self.settings.merge(other.settings) {
+            source(format!("settings.{setting}"));
+        }
+        for (name, hook) in other.hooks {
+            if !self.hooks.contains_key(&name) {
+                source(format!("hooks.{name}"));
+            }
+            let base = self.hooks.entry(name.clone()).or_insert_with(|| Hook {
+                name: name.clone(),
+                ..Default::default()
+            });
+            if hook.fix.is_some() {
+                source(format!("hooks.{name}.fix"));
+                base.fix = hook.fix;
+            }
+            if hook.stash.is_some() {
+                source(format!("hooks.{name}.stash"));
+                base.stash = hook.stash;
+            }
+            if hook.check_staged.is_some() {
+                source(format!("hooks.{name}.check_staged"));
+                base.check_staged = hook.check_staged;
+            }
+            if hook.timeout.is_some() {
+                source(format!("hooks.{name}.timeout"));
+                base.timeout = hook.timeout;
+            }
+            for (step_name, mut step) in hook.steps {
+                source(format!("hooks.{name}.steps.{step_name}"));
+                match &mut step {
+                    StepOrGroup::Step(s) if s.enabled == Some(false) => {
+                        base.steps.shift_remove(&step_name);
+                        continue;
+                    }
+                    StepOrGroup::Group(group) => {
+                        group.steps.retain(|_, s| s.enabled != Some(false));
+                    }
+                    StepOrGroup::Step(_) => {}
+                }
+                base.steps.insert(step_name, step);
+            }
+        }
+    }
+
+    /// Every profile declared in `profiles` or used by a step
+    pub fn known_profiles(&self) -> IndexSet<String> {
+        let steps = self
+            .hooks
+            .values()
+            .flat_map(|hook| hook.steps.values())
+            .flat_map(|step| match step {
+                StepOrGroup::Step(step) => vec![step.as_ref()],
+                StepOrGroup::Group(group) => group.steps.values().collect(),
+            })
+            .flat_map(|step| step.profiles.iter().flatten())
+            .map(|p| p.strip_prefix('!').unwrap_or(p).to_string());
+        self.profiles.keys().cloned().chain(steps).collect()
+    }
+
+    fn check_cli_profiles(&self) -> Result<()> {
+        let known = self.known_profiles();
+        let unknown = Settings::cli_profiles()
+            .into_iter()
+            // --slow works without the config mentioning it
+            .filter(|p| p != "slow" && !known.contains(p))
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            bail!(
+                "unknown profile: {}\navailable profiles: {}",
+                unknown.join(", "),
+                known.iter().join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Where parsed config files are cached, see `CachedConfig`
+    pub fn cache_dir() -> PathBuf {
+        env::HK_CACHE_DIR.join("configs")
+    }
+
+    /// Reads a config file along with the files and env vars it depends on
+    fn read(path: &Path) -> Result<(Self, Dependencies)> {
         let ext = path.extension().unwrap_or_default().to_str().unwrap();
+        let mut deps = Dependencies::default();
+        if ext != "pkl" {
+            deps.add_file(path);
+        }
         let mut config: Config = match ext {
             "toml" => {
                 let raw = xx::file::read_to_string(path)?;
@@ -54,27 +230,16 @@ impl Config {
                 serde_json::from_str(&raw)?
             }
             "pkl" => {
-                match parse_pkl("pkl", path) {
-                    Ok(raw) => raw,
-                    Err(err) => {
-                        // if pkl bin is not installed
-                        if which::which("pkl").is_err() {
-                            if let Ok(out) = parse_pkl("mise x -- pkl", path) {
-                                return Ok(out);
-                            };
-                            bail!("install pkl cli to use pkl config files https://pkl-lang.org/");
-                        } else {
-                            return Err(err).wrap_err("failed to read pkl config file");
-                        }
-                    }
-                }
+                let (config, pkl_deps) = pkl::eval(path)?;
+                deps = pkl_deps;
+                config
             }
             _ => {
                 bail!("Unsupported file extension: {}", ext);
             }
         };
         config.init(path)?;
-        Ok(config)
+        Ok((config, deps))
     }
 
     fn init(&mut self, path: &Path) -> Result<()> {
@@ -82,21 +247,10 @@ impl Config {
         if let Some(min_hk_version) = &self.min_hk_version {
             version::version_cmp_or_bail(min_hk_version)?;
         }
-        for (name, hook) in self.hooks.iter_mut() {
-            hook.init(name);
-        }
-        for (key, value) in self.env.iter() {
-            unsafe { std::env::set_var(key, value) };
-        }
         Ok(())
     }
 }
 
-fn parse_pkl<T: DeserializeOwned>(bin: &str, path: &Path) -> Result<T> {
-    let json = xx::process::sh(&format!("{bin} eval -f json {}", path.display()))?;
-    serde_json::from_str(&json).wrap_err("failed to parse pkl config file")
-}
-
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 #[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
@@ -109,6 +263,53 @@ pub struct Config {
     pub path: PathBuf,
     #[serde(default)]
     pub env: IndexMap<String, String>,
+    #[serde(default)]
+    pub settings: ConfigSettings,
+    #[serde(default)]
+    pub profiles: IndexMap<String, Profile>,
+    /// the file each value came from when configs are layered, e.g.: `hooks.pre-commit.steps.prettier`
+    #[serde(skip)]
+    pub sources: IndexMap<String, PathBuf>,
+}
+
+/// A parsed config file along with the path it was read from since `Config::path` isn't serialized
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedConfig {
+    pub path: PathBuf,
+    pub config: Config,
+    /// every file read while evaluating the config and its mtime when it was read
+    #[serde(default)]
+    pub files: IndexMap<PathBuf, Option<SystemTime>>,
+    /// every env var read while evaluating the config and its value when it was read
+    #[serde(default)]
+    pub env: IndexMap<String, Option<String>>,
+    /// the config reads something which is only known once it is evaluated
+    #[serde(default)]
+    pub dynamic: bool,
+}
+
+impl CachedConfig {
+    fn read(path: &Path) -> Result<Self> {
+        let (config, deps) = Config::read(path)
+            .wrap_err_with(|| format!("Failed to read config file: {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            config,
+            files: deps.files,
+            env: deps.env,
+            dynamic: deps.dynamic,
+        })
+    }
+
+    fn is_fresh(&self) -> bool {
+        !self.dynamic
+            && self.files.iter().all(|(f, m)| mtime(f) == *m)
+            && self.env.iter().all(|(k, v)| std::env::var(k).ok() == *v)
+    }
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
 }
 
 impl std::fmt::Display for Config {
@@ -119,7 +320,29 @@ impl std::fmt::Display for Config {
 
 impl Config {
     pub fn validate(&self) -> Result<()> {
-        // TODO: validate config
+        let known = self.known_profiles();
+        let errors = self
+            .hooks
+            .iter()
+            .flat_map(|(name, hook)| {
+                hook.validate()
+                    .into_iter()
+                    .map(move |err| format!("{name}: {}", err.replace('\n', "\n    ")))
+            })
+            .chain(self.profiles.iter().flat_map(|(name, profile)| {
+                profile
+                    .validate(&known)
+                    .into_iter()
+                    .map(move |err| format!("profile {name}: {err}"))
+            }))
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            bail!(
+                "{} is invalid:\n  {}",
+                xx::file::display_path(&self.path),
+                errors.join("\n  ")
+            );
+        }
         Ok(())
     }
 }
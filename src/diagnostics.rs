@@ -0,0 +1,292 @@
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+/// How to read diagnostics out of a step's output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, strum::Display)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum OutputParser {
+    /// lines matching `output_pattern` or [DEFAULT_PATTERN]
+    Regex,
+    /// `eslint --format json`
+    EslintJson,
+    /// `ruff check --output-format json`
+    RuffJson,
+    /// `shellcheck --format json` or `--format json1`
+    ShellcheckJson,
+}
+
+/// `file:line:col: message` where the column and a leading "error:"/"warning:" are optional
+pub const DEFAULT_PATTERN: &str = r"^(?P<file>[^:\s][^:]*):(?P<line>\d+)(?::(?P<col>\d+))?:\s*(?:(?P<level>error|warning|note|info):\s*)?(?P<message>.+)$";
+
+/// A problem a linter reported at a location in a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub level: Level,
+    pub message: String,
+    pub rule: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl OutputParser {
+    /// `dir` is where the tool ran, relative paths in its output are relative to it
+    pub fn parse(
+        &self,
+        pattern: Option<&str>,
+        output: &str,
+        dir: Option<&Path>,
+    ) -> Result<Vec<Diagnostic>> {
+        let mut diagnostics = match self {
+            OutputParser::Regex => parse_regex(pattern.unwrap_or(DEFAULT_PATTERN), output)?,
+            OutputParser::EslintJson => parse_eslint(output)?,
+            OutputParser::RuffJson => parse_ruff(output)?,
+            OutputParser::ShellcheckJson => parse_shellcheck(output)?,
+        };
+        for diagnostic in &mut diagnostics {
+            diagnostic.file = match dir {
+                Some(dir) => relative(&dir.join(&diagnostic.file)),
+                None => relative(&diagnostic.file),
+            };
+        }
+        Ok(diagnostics)
+    }
+}
+
+/// Named groups: `file` and `message` are required, `line`, `col`, `level` and `rule` are optional
+fn parse_regex(pattern: &str, output: &str) -> Result<Vec<Diagnostic>> {
+    let re = Regex::new(&format!("(?m){pattern}"))?;
+    Ok(re
+        .captures_iter(&console::strip_ansi_codes(output))
+        .filter_map(|caps| {
+            let number = |name| caps.name(name).and_then(|m| m.as_str().parse().ok());
+            Some(Diagnostic {
+                file: PathBuf::from(caps.name("file")?.as_str().trim()),
+                line: number("line"),
+                column: number("col"),
+                level: match caps.name("level").map(|m| m.as_str().to_lowercase()) {
+                    Some(level) if level.starts_with("warn") => Level::Warning,
+                    Some(level) if level == "note" || level == "info" => Level::Note,
+                    _ => Level::Error,
+                },
+                message: caps.name("message")?.as_str().trim().to_string(),
+                rule: caps.name("rule").map(|m| m.as_str().to_string()),
+            })
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EslintFile {
+    file_path: PathBuf,
+    messages: Vec<EslintMessage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EslintMessage {
+    rule_id: Option<String>,
+    severity: u8,
+    message: String,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+fn parse_eslint(output: &str) -> Result<Vec<Diagnostic>> {
+    let files: Vec<EslintFile> = serde_json::from_str(json(output))?;
+    Ok(files
+        .into_iter()
+        .flat_map(|file| {
+            let path = file.file_path;
+            file.messages.into_iter().map(move |m| Diagnostic {
+                file: path.clone(),
+                line: m.line,
+                column: m.column,
+                level: if m.severity >= 2 {
+                    Level::Error
+                } else {
+                    Level::Warning
+                },
+                message: m.message,
+                rule: m.rule_id,
+            })
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct RuffMessage {
+    code: Option<String>,
+    message: String,
+    filename: PathBuf,
+    location: Option<RuffLocation>,
+}
+
+#[derive(Deserialize)]
+struct RuffLocation {
+    row: usize,
+    column: usize,
+}
+
+fn parse_ruff(output: &str) -> Result<Vec<Diagnostic>> {
+    let messages: Vec<RuffMessage> = serde_json::from_str(json(output))?;
+    Ok(messages
+        .into_iter()
+        .map(|m| Diagnostic {
+            file: m.filename,
+            line: m.location.as_ref().map(|l| l.row),
+            column: m.location.as_ref().map(|l| l.column),
+            level: Level::Error,
+            message: m.message,
+            rule: m.code,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ShellcheckOutput {
+    Json(Vec<ShellcheckComment>),
+    Json1 { comments: Vec<ShellcheckComment> },
+}
+
+#[derive(Deserialize)]
+struct ShellcheckComment {
+    file: PathBuf,
+    line: usize,
+    column: usize,
+    level: String,
+    code: u32,
+    message: String,
+}
+
+fn parse_shellcheck(output: &str) -> Result<Vec<Diagnostic>> {
+    let comments = match serde_json::from_str(json(output))? {
+        ShellcheckOutput::Json(comments) => comments,
+        ShellcheckOutput::Json1 { comments } => comments,
+    };
+    Ok(comments
+        .into_iter()
+        .map(|c| Diagnostic {
+            file: c.file,
+            line: Some(c.line),
+            column: Some(c.column),
+            level: match c.level.as_str() {
+                "error" => Level::Error,
+                "warning" => Level::Warning,
+                _ => Level::Note,
+            },
+            message: c.message,
+            rule: Some(format!("SC{}", c.code)),
+        })
+        .collect())
+}
+
+/// tools print nothing at all instead of an empty array when there are no problems
+fn json(output: &str) -> &str {
+    match output.trim() {
+        "" => "[]",
+        output => output,
+    }
+}
+
+fn relative(path: &Path) -> PathBuf {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok())
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_regex() {
+        let output = "src/a.sh:3:5: missing quote\nsomething else\nb.py:10: bad name\n";
+        let diagnostics = OutputParser::Regex.parse(None, output, None).unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, PathBuf::from("src/a.sh"));
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert_eq!(diagnostics[0].column, Some(5));
+        assert_eq!(diagnostics[0].message, "missing quote");
+        assert_eq!(diagnostics[1].column, None);
+        assert_eq!(diagnostics[1].level, Level::Error);
+        let diagnostics = OutputParser::Regex
+            .parse(None, "c.c:1:2: warning: unused", None)
+            .unwrap();
+        assert_eq!(diagnostics[0].level, Level::Warning);
+        assert_eq!(diagnostics[0].message, "unused");
+    }
+
+    #[test]
+    fn test_parse_eslint() {
+        let output = r#"[{"filePath":"a.js","messages":[{"ruleId":"no-unused-vars","severity":1,"message":"x is unused","line":1,"column":7}]}]"#;
+        let diagnostics = OutputParser::EslintJson.parse(None, output, None).unwrap();
+        assert_eq!(diagnostics[0].level, Level::Warning);
+        assert_eq!(diagnostics[0].rule.as_deref(), Some("no-unused-vars"));
+    }
+
+    #[test]
+    fn test_parse_ruff() {
+        let output = r#"[{"code":"F401","message":"os imported but unused","filename":"a.py","location":{"row":1,"column":8}}]"#;
+        let diagnostics = OutputParser::RuffJson.parse(None, output, None).unwrap();
+        assert_eq!(diagnostics[0].line, Some(1));
+        assert_eq!(diagnostics[0].rule.as_deref(), Some("F401"));
+    }
+
+    #[test]
+    fn test_parse_shellcheck() {
+        let comment = r#"{"file":"a.sh","line":2,"column":6,"level":"warning","code":2086,"message":"Double quote"}"#;
+        for output in [
+            format!("[{comment}]"),
+            format!(r#"{{"comments":[{comment}]}}"#),
+        ] {
+            let diagnostics = OutputParser::ShellcheckJson
+                .parse(None, &output, None)
+                .unwrap();
+            assert_eq!(diagnostics[0].rule.as_deref(), Some("SC2086"));
+            assert_eq!(diagnostics[0].level, Level::Warning);
+        }
+    }
+
+    #[test]
+    fn test_parse_dir() {
+        let cwd = std::env::current_dir().unwrap();
+        let output = format!(
+            "a.sh:1: relative\n{}:2: absolute",
+            cwd.join("b.sh").display()
+        );
+        let diagnostics = OutputParser::Regex
+            .parse(None, &output, Some(Path::new("sub")))
+            .unwrap();
+        assert_eq!(diagnostics[0].file, PathBuf::from("sub/a.sh"));
+        assert_eq!(diagnostics[1].file, PathBuf::from("b.sh"));
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert!(
+            OutputParser::EslintJson
+                .parse(None, "", None)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -17,6 +17,13 @@ pub static HK_STATE_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
             .join("hk"),
     )
 });
+pub static HK_CONFIG_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    var_path("HK_CONFIG_DIR").unwrap_or(
+        var_path("XDG_CONFIG_HOME")
+            .unwrap_or(HOME_DIR.join(".config"))
+            .join("hk"),
+    )
+});
 pub static HK_FILE: LazyLock<Option<String>> = LazyLock::new(|| var("HK_FILE").ok());
 pub static HK_CACHE_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
     var_path("HK_CACHE_DIR").unwrap_or(
@@ -36,6 +43,7 @@ pub static HK_LOG_FILE: LazyLock<PathBuf> =
     LazyLock::new(|| var_path("HK_LOG_FILE").unwrap_or(HK_STATE_DIR.join("hk.log")));
 
 pub static HK_LIBGIT2: LazyLock<bool> = LazyLock::new(|| !var_false("HK_LIBGIT2"));
+pub static HK_PKL_SERVER: LazyLock<bool> = LazyLock::new(|| !var_false("HK_PKL_SERVER"));
 pub static HK_HIDE_WHEN_DONE: LazyLock<bool> = LazyLock::new(|| var_true("HK_HIDE_WHEN_DONE"));
 pub static HK_CHECK_FIRST: LazyLock<bool> = LazyLock::new(|| !var_false("HK_CHECK_FIRST"));
 pub static HK_STASH: LazyLock<Option<StashMethod>> = LazyLock::new(|| {
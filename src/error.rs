@@ -1,5 +1,7 @@
 //pub use std::error::*;
 
+use ensembler::CmdResult;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("check list failed: {source}")]
@@ -8,4 +10,27 @@ pub enum Error {
         source: eyre::Error,
         stdout: String,
     },
+    #[error("timed out after {}s", .0.as_secs_f64())]
+    TimedOut(std::time::Duration),
+    #[error("{}{message}", location.as_ref().map(|l| format!("{l}: ")).unwrap_or_default())]
+    PklEval {
+        location: Option<String>,
+        message: String,
+    },
+    /// the hook's failures have been printed in its summary
+    #[error("{hook} failed: {source}")]
+    HookFailed {
+        hook: String,
+        #[source]
+        source: eyre::Error,
+    },
+}
+
+/// Finds the failed command in an error chain, if any
+pub fn script_failed(err: &eyre::Report) -> Option<&(String, Vec<String>, String, CmdResult)> {
+    err.chain()
+        .find_map(|e| match e.downcast_ref::<ensembler::Error>() {
+            Some(ensembler::Error::ScriptFailed(e)) => Some(e.as_ref()),
+            _ => None,
+        })
 }
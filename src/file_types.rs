@@ -0,0 +1,168 @@
+//! Detects what language a file is written in from its extension, its filename or its shebang line.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum FileType {
+    Dockerfile,
+    Go,
+    Javascript,
+    Json,
+    Lua,
+    Make,
+    Markdown,
+    Perl,
+    Php,
+    Python,
+    Ruby,
+    Rust,
+    Shell,
+    Toml,
+    Typescript,
+    Yaml,
+}
+
+impl FileType {
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            FileType::Dockerfile => &["dockerfile"],
+            FileType::Go => &["go"],
+            FileType::Javascript => &["js", "mjs", "cjs", "jsx"],
+            FileType::Json => &["json"],
+            FileType::Lua => &["lua"],
+            FileType::Make => &["mk"],
+            FileType::Markdown => &["md", "markdown"],
+            FileType::Perl => &["pl", "pm"],
+            FileType::Php => &["php"],
+            FileType::Python => &["py", "pyi"],
+            FileType::Ruby => &["rb", "rake", "gemspec"],
+            FileType::Rust => &["rs"],
+            FileType::Shell => &["sh", "bash", "zsh", "ksh"],
+            FileType::Toml => &["toml"],
+            FileType::Typescript => &["ts", "mts", "cts", "tsx"],
+            FileType::Yaml => &["yml", "yaml"],
+        }
+    }
+
+    fn filenames(&self) -> &'static [&'static str] {
+        match self {
+            FileType::Dockerfile => &["Dockerfile", "Containerfile"],
+            FileType::Make => &["Makefile", "makefile", "GNUmakefile"],
+            FileType::Ruby => &["Gemfile", "Rakefile"],
+            FileType::Shell => &[
+                ".bashrc",
+                ".bash_profile",
+                ".bash_logout",
+                ".profile",
+                ".zshrc",
+                ".zshenv",
+                ".zprofile",
+            ],
+            _ => &[],
+        }
+    }
+
+    /// Interpreters in a shebang line, without any version suffix
+    fn interpreters(&self) -> &'static [&'static str] {
+        match self {
+            FileType::Javascript => &["node", "nodejs"],
+            FileType::Lua => &["lua", "luajit"],
+            FileType::Make => &["make"],
+            FileType::Perl => &["perl"],
+            FileType::Php => &["php"],
+            FileType::Python => &["python", "pypy"],
+            FileType::Ruby => &["ruby"],
+            FileType::Shell => &["sh", "bash", "zsh", "ksh", "dash", "ash", "mksh"],
+            FileType::Typescript => &["deno", "ts-node", "tsx", "bun"],
+            _ => &[],
+        }
+    }
+
+    /// Whether `path` is any of `types`, only reading the file if its name doesn't match
+    pub fn matches_any(types: &[FileType], path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let by_name = types.iter().any(|t| {
+            t.filenames().contains(&name)
+                || extension
+                    .as_deref()
+                    .is_some_and(|e| t.extensions().contains(&e))
+        });
+        if by_name {
+            return true;
+        }
+        let Some(interpreter) = shebang_interpreter(path) else {
+            return false;
+        };
+        types
+            .iter()
+            .any(|t| t.interpreters().contains(&interpreter.as_str()))
+    }
+}
+
+/// Returns the interpreter named in a file's shebang line without any version suffix,
+/// e.g. "python" for `#!/usr/bin/env python3.12`
+fn shebang_interpreter(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let mut line = String::new();
+    // a shebang line longer than this is either not a shebang or not one we know
+    BufReader::new(file.take(256)).read_line(&mut line).ok()?;
+    let line = line.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        // skips options such as `env -S`
+        program = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    Some(program.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(name: &str, content: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join("hk-file-types").join(name);
+        xx::file::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_extension_and_filename() {
+        let sh = write("test_extension/build.SH", "");
+        let gemfile = write("test_extension/Gemfile", "");
+        assert!(FileType::matches_any(&[FileType::Shell], &sh));
+        assert!(FileType::matches_any(&[FileType::Ruby], &gemfile));
+        assert!(!FileType::matches_any(&[FileType::Python], &sh));
+    }
+
+    #[test]
+    fn test_shebang() {
+        let bash = write("test_shebang/a", "#!/bin/bash\necho hi\n");
+        let python = write("test_shebang/b", "#!/usr/bin/env python3.12\n");
+        let node = write("test_shebang/c", "#!/usr/bin/env -S node --no-warnings\n");
+        let none = write("test_shebang/d", "echo hi\n");
+        assert!(FileType::matches_any(&[FileType::Shell], &bash));
+        assert!(FileType::matches_any(&[FileType::Python], &python));
+        assert!(FileType::matches_any(&[FileType::Javascript], &node));
+        assert!(!FileType::matches_any(
+            &[FileType::Shell, FileType::Python],
+            &none
+        ));
+    }
+}
@@ -16,11 +16,15 @@ use serde::{Deserialize, Serialize};
 use std::os::unix::ffi::OsStringExt;
 use xx::file::display_path;
 
-use crate::env;
+use crate::{env, stash_journal::StashJournal};
 
 pub struct Git {
     repo: Option<Repository>,
     stash: Option<StashType>,
+    /// removed once the stash is popped, see `StashJournal`
+    journal: Option<StashJournal>,
+    /// removed from the index while stashing and added back when popping
+    intent_to_add: Vec<PathBuf>,
     root: PathBuf,
     patch_file: OnceCell<PathBuf>,
 }
@@ -31,7 +35,9 @@ enum StashType {
     Git,
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize, strum::EnumString)]
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize, strum::EnumString, strum::Display,
+)]
 #[serde(rename_all = "kebab-case")]
 #[strum(serialize_all = "kebab-case")]
 pub enum StashMethod {
@@ -64,10 +70,16 @@ impl Git {
             root,
             repo,
             stash: None,
+            journal: None,
+            intent_to_add: vec![],
             patch_file: OnceCell::new(),
         })
     }
 
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
     pub fn patch_file(&self) -> &Path {
         self.patch_file.get_or_init(|| {
             let name = self
@@ -261,6 +273,22 @@ impl Git {
         job: &ProgressJob,
         method: StashMethod,
         status: &GitStatus,
+    ) -> Result<()> {
+        let result = self.try_stash_unstaged(job, method, status);
+        if result.is_err() && self.stash.is_none() {
+            // nothing was stashed so only intent-to-add files need to be added back
+            if let Err(err) = self.pop_stash() {
+                warn!("failed to restore intent-to-add files: {err:?}");
+            }
+        }
+        result
+    }
+
+    fn try_stash_unstaged(
+        &mut self,
+        job: &ProgressJob,
+        method: StashMethod,
+        status: &GitStatus,
     ) -> Result<()> {
         // Skip stashing if there's no initial commit yet or auto-stash is disabled
         if method == StashMethod::None {
@@ -276,15 +304,36 @@ impl Git {
         job.set_status(ProgressStatus::Running);
 
         job.prop("files", &status.unstaged_files.len());
-        // TODO: if any intent_to_add files exist, run `git rm --cached -- <file>...` then `git add --intent-to-add -- <file>...` when unstashing
-        // let intent_to_add = self.intent_to_add_files()?;
-        // see https://github.com/pre-commit/pre-commit/blob/main/pre_commit/staged_files_only.py
         if status.unstaged_files.is_empty() {
             job.prop("message", "No unstaged changes to stash");
             job.set_status(ProgressStatus::Done);
             return Ok(());
         }
 
+        // intent-to-add files (`git add -N`) confuse `git stash` and `git diff` so they're
+        // removed from the index until the stash is popped
+        // see https://github.com/pre-commit/pre-commit/blob/main/pre_commit/staged_files_only.py
+        let mut status = status.clone();
+        self.intent_to_add = self.intent_to_add_files()?;
+        if !self.intent_to_add.is_empty() {
+            // so they can be added back if hk is killed before popping the stash
+            self.write_journal(StashJournal::new(&self.root, StashMethod::None, None))?;
+            debug!("removing intent-to-add files: {:?}", self.intent_to_add);
+            xx::process::cmd("git", ["rm", "--cached", "--quiet", "--"])
+                .args(&self.intent_to_add)
+                .run()?;
+            for file in &self.intent_to_add {
+                status.unstaged_files.remove(file);
+                status.modified_files.remove(file);
+            }
+            if status.unstaged_files.is_empty() {
+                job.prop("message", "No unstaged changes to stash");
+                job.set_status(ProgressStatus::Done);
+                return Ok(());
+            }
+        }
+        let status = &status;
+
         // if let Ok(msg) = self.head_commit_message() {
         //     if msg.contains("Merge") {
         //         return Ok(());
@@ -299,13 +348,35 @@ impl Git {
                 ),
             );
             job.update();
-            self.build_diff(status)?
+            let stash = self.build_diff(status)?;
+            if stash.is_some() {
+                let patch_file = self.patch_file().to_path_buf();
+                self.write_journal(StashJournal::new(
+                    &self.root,
+                    StashMethod::PatchFile,
+                    Some(patch_file),
+                ))?;
+            }
+            stash
         } else {
             job.prop("message", "Running git stash");
             job.update();
-            self.push_stash(status)?
+            self.write_journal(StashJournal::new(&self.root, StashMethod::Git, None))?;
+            let stash = self.push_stash(status)?;
+            if let (true, Some(commit)) = (stash.is_some(), self.stash_commit()) {
+                let mut journal = self.journal.clone().unwrap();
+                journal.commit = Some(commit);
+                self.write_journal(journal)?;
+            }
+            stash
         };
         if self.stash.is_none() {
+            // the journal is kept while intent-to-add files are out of the index
+            if self.intent_to_add.is_empty() {
+                self.remove_journal();
+            } else {
+                self.write_journal(StashJournal::new(&self.root, StashMethod::None, None))?;
+            }
             job.prop("message", "No unstaged files to stash");
             job.set_status(ProgressStatus::Done);
             return Ok(());
@@ -429,9 +500,76 @@ impl Git {
         }
     }
 
+    /// Written before the working tree is modified so the changes can be recovered if hk is killed
+    /// Writes the journal, replacing the entry already written for this stash if any
+    fn write_journal(&mut self, mut journal: StashJournal) -> Result<()> {
+        if let Some(existing) = &self.journal {
+            journal.id = existing.id.clone();
+            journal.created = existing.created;
+        }
+        journal.intent_to_add = self.intent_to_add.clone();
+        journal.write()?;
+        self.journal = Some(journal);
+        Ok(())
+    }
+
+    fn remove_journal(&mut self) {
+        if let Some(journal) = self.journal.take() {
+            if let Err(err) = journal.remove() {
+                warn!("failed to remove stash journal: {err:?}");
+            }
+        }
+    }
+
+    /// The commit of the most recent `git stash`
+    fn stash_commit(&self) -> Option<String> {
+        if let Some(repo) = &self.repo {
+            repo.refname_to_id("refs/stash")
+                .ok()
+                .map(|oid| oid.to_string())
+        } else {
+            xx::process::cmd("git", ["rev-parse", "-q", "--verify", "refs/stash"])
+                .read()
+                .ok()
+        }
+    }
+
+    /// Files added with `git add --intent-to-add`
+    fn intent_to_add_files(&self) -> Result<Vec<PathBuf>> {
+        let output = xx::process::cmd(
+            "git",
+            [
+                "diff",
+                "--no-ext-diff",
+                "--ignore-submodules",
+                "--diff-filter=A",
+                "--name-only",
+                "-z",
+            ],
+        )
+        .read()?;
+        Ok(output
+            .split('\0')
+            .filter(|f| !f.is_empty())
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    fn restore_intent_to_add(&mut self) -> Result<()> {
+        let intent_to_add = std::mem::take(&mut self.intent_to_add);
+        if !intent_to_add.is_empty() {
+            debug!("restoring intent-to-add files: {intent_to_add:?}");
+            xx::process::cmd("git", ["add", "--intent-to-add", "--"])
+                .args(&intent_to_add)
+                .run()?;
+        }
+        Ok(())
+    }
+
     pub fn pop_stash(&mut self) -> Result<()> {
         let Some(diff) = self.stash.take() else {
-            return Ok(());
+            self.remove_journal();
+            return self.restore_intent_to_add();
         };
         let job: Arc<ProgressJob>;
 
@@ -485,8 +623,10 @@ impl Git {
                 xx::process::sh("git stash pop")?;
             }
         }
+        self.remove_journal();
         job.set_status(ProgressStatus::Done);
-        Ok(())
+        // after popping since `git stash` may have stashed them as untracked files
+        self.restore_intent_to_add()
     }
 
     pub fn add(&self, pathspecs: &[PathBuf]) -> Result<()> {
@@ -508,6 +648,80 @@ impl Git {
         }
     }
 
+    /// The contents of `path` in the index
+    pub fn staged_contents(&self, path: &Path) -> Result<Vec<u8>> {
+        if let Some(repo) = &self.repo {
+            let index = repo.index().wrap_err("failed to get index")?;
+            let entry = index
+                .get_path(path, 0)
+                .ok_or_else(|| eyre!("{} is not staged", display_path(path)))?;
+            let blob = repo.find_blob(entry.id).wrap_err("failed to find blob")?;
+            Ok(blob.content().to_vec())
+        } else {
+            let output = xx::process::cmd("git", ["cat-file", "blob"])
+                .arg(format!(":{}", path.display()))
+                .stdout_capture()
+                .run()?;
+            Ok(output.stdout)
+        }
+    }
+
+    /// Applies the changes between `base` and `other` to `current`, like `git merge-file`.
+    /// Returns `None` if they conflict.
+    pub fn merge_file(&self, current: &Path, base: &Path, other: &Path) -> Result<Option<Vec<u8>>> {
+        let output = std::process::Command::new("git")
+            .args(["merge-file", "-p", "-q"])
+            .args([current, base, other])
+            .output()
+            .wrap_err("failed to run git merge-file")?;
+        match output.status.code() {
+            Some(0) => Ok(Some(output.stdout)),
+            Some(n) if n > 0 => Ok(None),
+            _ => Err(eyre!(
+                "git merge-file failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        }
+    }
+
+    /// Replaces the staged contents of `path` without touching the working tree
+    pub fn stage_contents(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        if let Some(repo) = &self.repo {
+            let mut index = repo.index().wrap_err("failed to get index")?;
+            let entry = index
+                .get_path(path, 0)
+                .ok_or_else(|| eyre!("{} is not staged", display_path(path)))?;
+            index
+                .add_frombuffer(&entry, contents)
+                .wrap_err("failed to add contents to index")?;
+            index.write().wrap_err("failed to write index")?;
+        } else {
+            let output = xx::process::cmd("git", ["ls-files", "--stage", "--"])
+                .arg(path)
+                .read()?;
+            let mode = output
+                .split_whitespace()
+                .next()
+                .ok_or_else(|| eyre!("{} is not staged", display_path(path)))?;
+            let mut child = std::process::Command::new("git")
+                .args(["hash-object", "-w", "--no-filters", "--stdin"])
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .spawn()
+                .wrap_err("failed to run git hash-object")?;
+            std::io::Write::write_all(&mut child.stdin.take().unwrap(), contents)?;
+            let output = child.wait_with_output()?;
+            if !output.status.success() {
+                return Err(eyre!("git hash-object failed"));
+            }
+            let oid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            xx::process::cmd("git", ["update-index", "--cacheinfo"])
+                .arg(format!("{mode},{oid},{}", path.display()))
+                .run()?;
+        }
+        Ok(())
+    }
+
     pub fn files_between_refs(&self, from_ref: &str, to_ref: Option<&str>) -> Result<Vec<PathBuf>> {
         let to_ref = to_ref.unwrap_or("HEAD");
         if let Some(repo) = &self.repo {
@@ -587,3 +801,14 @@ pub(crate) struct GitStatus {
     pub untracked_files: BTreeSet<PathBuf>,
     pub modified_files: BTreeSet<PathBuf>,
 }
+
+impl GitStatus {
+    /// Files with both staged and unstaged changes
+    pub fn partially_staged_files(&self) -> BTreeSet<PathBuf> {
+        self.staged_files
+            .intersection(&self.unstaged_files)
+            .filter(|f| !self.untracked_files.contains(*f))
+            .cloned()
+            .collect()
+    }
+}
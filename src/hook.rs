@@ -1,12 +1,15 @@
 use clx::progress::{ProgressJob, ProgressJobBuilder, ProgressOutput, ProgressStatus};
+use eyre::bail;
 use indexmap::IndexMap;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use serde_with::{DurationSecondsWithFrac, formats::Flexible, serde_as};
 use std::{
     collections::{BTreeSet, HashSet},
     ffi::OsString,
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant},
 };
 use tokio::{
     signal,
@@ -16,18 +19,30 @@ use tokio_util::sync::CancellationToken;
 
 use crate::{
     Result, env,
+    error::Error,
     file_rw_locks::FileRwLocks,
     git::{Git, GitStatus, StashMethod},
     glob,
     hook_options::HookOptions,
+    junit,
+    plan::HookPlan,
+    reporter::Reporter,
+    results::{JobResult, Results},
+    sarif,
     settings::Settings,
-    step::{CheckType, EXPR_CTX, RunType, Step},
+    staged_files::StagedFiles,
+    stash_journal::StashJournal,
+    step::{CheckType, EXPR_CTX, RunType, SkipReason, Step},
     step_context::StepContext,
+    step_depends::find_cycle,
     step_group::{StepGroup, StepGroupContext},
+    summary::Summary,
+    timings::{TimingKind, Timings},
     ui::style,
     version,
 };
 
+#[serde_as]
 #[derive(Debug, Clone, Default, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(rename_all = "snake_case")]
 #[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
@@ -38,6 +53,12 @@ pub struct Hook {
     pub steps: IndexMap<String, StepOrGroup>,
     pub fix: Option<bool>,
     pub stash: Option<StashMethod>,
+    /// check steps get the staged contents of partially staged files when not stashing
+    pub check_staged: Option<bool>,
+    /// default timeout for steps which don't set their own
+    #[serde_as(as = "Option<DurationSecondsWithFrac<f64, Flexible>>")]
+    #[serde(default)]
+    pub timeout: Option<Duration>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
@@ -54,6 +75,19 @@ impl StepOrGroup {
             StepOrGroup::Group(group) => group.init(name),
         }
     }
+
+    fn set_default_timeout(&mut self, timeout: Duration) {
+        match self {
+            StepOrGroup::Step(step) => {
+                step.timeout.get_or_insert(timeout);
+            }
+            StepOrGroup::Group(group) => {
+                for step in group.steps.values_mut() {
+                    step.timeout.get_or_insert(timeout);
+                }
+            }
+        }
+    }
 }
 pub struct HookContext {
     pub file_locks: FileRwLocks,
@@ -69,6 +103,16 @@ pub struct HookContext {
     total_jobs: std::sync::Mutex<usize>,
     completed_jobs: std::sync::Mutex<usize>,
     expr_ctx: std::sync::Mutex<expr::Context>,
+    pub summary: Summary,
+    pub timings: Timings,
+    pub results: Results,
+    pub reporter: Reporter,
+    /// Whether to skip checks on files which already passed them, see `ResultCache`
+    pub cache: bool,
+    /// Partially staged files when not stashing, see `StagedFiles`
+    pub staged: Option<StagedFiles>,
+    /// Whether check steps get the staged contents of `staged`, see `Hook::check_staged`
+    pub check_staged: bool,
 }
 
 impl HookContext {
@@ -79,6 +123,7 @@ impl HookContext {
         tctx: crate::tera::Context,
         run_type: RunType,
         hk_progress: Option<Arc<ProgressJob>>,
+        timings: Timings,
     ) -> Self {
         let settings = Settings::get();
         let expr_ctx = EXPR_CTX.clone();
@@ -96,6 +141,13 @@ impl HookContext {
             semaphore: Arc::new(Semaphore::new(settings.jobs.get())),
             failed: CancellationToken::new(),
             expr_ctx: std::sync::Mutex::new(expr_ctx),
+            summary: Default::default(),
+            timings,
+            results: Default::default(),
+            reporter: Default::default(),
+            cache: true,
+            staged: None,
+            check_staged: false,
         }
     }
 
@@ -119,6 +171,11 @@ impl HookContext {
         }
     }
 
+    /// Partially staged files whose staged contents check steps get instead
+    pub fn staged_for_check(&self) -> Option<&StagedFiles> {
+        self.staged.as_ref().filter(|_| self.check_staged)
+    }
+
     pub fn expr_ctx(&self) -> expr::Context {
         self.expr_ctx.lock().unwrap().clone()
     }
@@ -155,7 +212,69 @@ impl Hook {
         self.name = hook_name.to_string();
         for (name, step) in self.steps.iter_mut() {
             step.init(name);
+            if let Some(timeout) = self.timeout {
+                step.set_default_timeout(timeout);
+            }
+        }
+    }
+
+    /// Returns a description of every problem found in the hook's steps
+    pub fn validate(&self) -> Vec<String> {
+        StepGroup::build_all(self.steps.values().cloned().collect())
+            .iter()
+            .flat_map(|group| group.validate())
+            .chain(self.depends_errors())
+            .chain(self.missing_depends())
+            .collect()
+    }
+
+    /// Steps may depend on steps in the same group or in an earlier group (which will have finished already).
+    /// Depending on a step in a later group or a cycle within a group would never finish.
+    fn depends_errors(&self) -> Vec<String> {
+        let groups = StepGroup::build_all(self.steps.values().cloned().collect());
+        let mut errors = vec![];
+        for (i, group) in groups.iter().enumerate() {
+            for step in group.steps.values() {
+                for dep in &step.depends {
+                    if groups[i + 1..].iter().any(|g| g.steps.contains_key(dep)) {
+                        errors.push(format!(
+                            "{step}: depends on {dep} which runs in a later group"
+                        ));
+                    }
+                }
+            }
+            if let Some(cycle) = find_cycle(&group.steps) {
+                errors.push(format!("dependency cycle: {}", cycle.join(" -> ")));
+            }
+        }
+        errors
+    }
+
+    /// Dependencies on steps which aren't in the hook are ignored when running it
+    fn missing_depends(&self) -> Vec<String> {
+        let steps = self
+            .steps
+            .values()
+            .flat_map(|s| match s {
+                StepOrGroup::Step(step) => vec![step.as_ref()],
+                StepOrGroup::Group(group) => group.steps.values().collect(),
+            })
+            .collect_vec();
+        let names = steps
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<HashSet<_>>();
+        let mut warnings = vec![];
+        for step in steps {
+            for dep in &step.depends {
+                if !names.contains(dep.as_str()) {
+                    warnings.push(format!(
+                        "{step}: depends on {dep} which is not a step in this hook"
+                    ));
+                }
+            }
         }
+        warnings
     }
 
     fn run_type(&self, opts: &HookOptions) -> RunType {
@@ -167,7 +286,8 @@ impl Hook {
         }
     }
 
-    fn get_step_groups(&self, run_type: RunType, opts: &HookOptions) -> Vec<StepGroup> {
+    /// Steps selected with `--step`, or all steps if none were given
+    fn selected_steps(&self, opts: &HookOptions) -> Vec<StepOrGroup> {
         let mut steps = self.steps.values().cloned().collect_vec();
         if !opts.step.is_empty() {
             steps = steps
@@ -181,18 +301,26 @@ impl Hook {
                 })
                 .collect_vec();
         }
-        let step_ok = |step: &Step| {
-            if step.run_cmd(run_type).is_none() {
-                debug!("{step}: skipping step due to no available run type");
-                false
-            } else if env::HK_SKIP_STEPS.contains(&step.name) {
-                debug!("{step}: skipping step due to HK_SKIP_STEPS");
+        steps
+    }
+
+    /// Returns the groups of steps to run along with the steps which will be skipped
+    fn get_step_groups(
+        &self,
+        run_type: RunType,
+        opts: &HookOptions,
+    ) -> (Vec<StepGroup>, Vec<(String, SkipReason)>) {
+        let mut skipped = vec![];
+        let mut step_ok = |step: &Step| match step.skip_reason(run_type) {
+            Some(reason) => {
+                debug!("{step}: skipping step due to {reason}");
+                skipped.push((step.name.clone(), reason));
                 false
-            } else {
-                step.is_profile_enabled()
             }
+            None => true,
         };
-        let steps = steps
+        let steps = self
+            .selected_steps(opts)
             .into_iter()
             .filter_map(|s| match s {
                 StepOrGroup::Step(ref step) => step_ok(step).then_some(s),
@@ -202,51 +330,108 @@ impl Hook {
                 }
             })
             .collect_vec();
-        StepGroup::build_all(steps)
+        (StepGroup::build_all(steps), skipped)
     }
 
     pub async fn plan(&self, opts: HookOptions) -> Result<()> {
+        if env::HK_SKIP_HOOK.contains(&self.name) {
+            warn!("{}: skipping hook due to HK_SKIP_HOOK", &self.name);
+            return Ok(());
+        }
         let run_type = self.run_type(&opts);
-        let groups = self.get_step_groups(run_type, &opts);
         let repo = Arc::new(Mutex::new(Git::new()?));
         let git_status = OnceCell::new();
-        let stash_method = env::HK_STASH.or(self.stash).unwrap_or(StashMethod::None);
+        let stash_method = env::HK_STASH
+            .or(self.stash)
+            .or(Settings::get().stash)
+            .unwrap_or(StashMethod::None);
         let progress = ProgressJobBuilder::new()
             .status(ProgressStatus::Hide)
             .build();
         let files = self
-            .file_list(&opts, repo.clone(), &git_status, stash_method, &progress)
+            .file_list(
+                &opts,
+                repo.clone(),
+                &git_status,
+                stash_method,
+                &progress,
+                &Timings::default(),
+            )
             .await?;
-        if files.is_empty() && can_exit_early(&groups, &files, run_type) {
+        // JSON consumers always get a plan, with every step skipped if there are no files
+        if !opts.json
+            && files.is_empty()
+            && can_exit_early(&self.get_step_groups(run_type, &opts).0, &files, run_type)
+        {
             info!("no files to run");
             return Ok(());
         }
-        if stash_method != StashMethod::None {
-            info!("stashing unstaged changes");
-        }
-        for group in groups {
-            group.plan().await?;
+        // skipped steps are kept so the plan can show why they will not run
+        let groups = StepGroup::build_all(self.selected_steps(&opts));
+        let hook_ctx = HookContext::new(
+            files,
+            repo,
+            groups,
+            opts.tctx,
+            run_type,
+            None,
+            Timings::default(),
+        );
+        let plan = HookPlan {
+            name: self.name.clone(),
+            run_type,
+            stash: stash_method,
+            groups: hook_ctx
+                .groups
+                .iter()
+                .map(|group| group.plan(&hook_ctx))
+                .collect::<Result<_>>()?,
+            files: hook_ctx.files(),
+        };
+        if opts.json {
+            println!("{}", serde_json::to_string_pretty(&plan)?);
+        } else {
+            println!("{plan}");
         }
         Ok(())
     }
 
     pub async fn run(&self, opts: HookOptions) -> Result<()> {
         let settings = Settings::get();
+        let start = Instant::now();
         if env::HK_SKIP_HOOK.contains(&self.name) {
             warn!("{}: skipping hook due to HK_SKIP_HOOK", &self.name);
+            self.write_reports(&opts, &Timings::default(), &[], start);
             return Ok(());
         }
+        let depends_errors = self.depends_errors();
+        if !depends_errors.is_empty() {
+            bail!(
+                "{}: {}",
+                self.name,
+                depends_errors.join(&format!("\n{}: ", self.name))
+            );
+        }
+        for warning in self.missing_depends() {
+            warn!("{}: {warning}", self.name);
+        }
         let run_type = self.run_type(&opts);
         let repo = Arc::new(Mutex::new(Git::new()?));
+        warn_leftover_stashes(repo.lock().await.root());
         let git_status = OnceCell::new();
-        let groups = self.get_step_groups(run_type, &opts);
-        let stash_method = env::HK_STASH.or(self.stash).unwrap_or(StashMethod::None);
+        let (groups, skipped) = self.get_step_groups(run_type, &opts);
+        let stash_method = env::HK_STASH
+            .or(self.stash)
+            .or(Settings::get().stash)
+            .unwrap_or(StashMethod::None);
         let hk_progress = self.start_hk_progress(run_type, groups.len());
         let file_progress = ProgressJobBuilder::new().body(
             "{{spinner()}} files - {{message}}{% if files is defined %} ({{files}} file{{files|pluralize}}){% endif %}"
         )
         .prop("message", "Fetching git status")
         .start();
+        let timings = Timings::default();
+        let files_start = Instant::now();
         let files = self
             .file_list(
                 &opts,
@@ -254,40 +439,86 @@ impl Hook {
                 &git_status,
                 stash_method,
                 &file_progress,
+                &timings,
             )
             .await?;
+        timings.record(TimingKind::Files, "files", files_start);
+        let check_staged = self.check_staged == Some(true);
+        let staged =
+            if stash_method == StashMethod::None && (check_staged || run_type == RunType::Fix) {
+                let git_status = fetch_git_status(&git_status, &repo, &timings).await?;
+                let partially_staged = git_status
+                    .partially_staged_files()
+                    .into_iter()
+                    .filter(|f| files.contains(f))
+                    .collect_vec();
+                debug!("partially staged files: {partially_staged:?}");
+                let staged = StagedFiles::new(&*repo.lock().await, &partially_staged)?;
+                (!staged.is_empty()).then_some(staged)
+            } else {
+                None
+            };
 
         if files.is_empty() && can_exit_early(&groups, &files, run_type) {
             info!("no files to run");
             if let Some(hk_progress) = &hk_progress {
                 hk_progress.set_status(ProgressStatus::Hide);
             }
+            let results = skipped
+                .iter()
+                .map(|(step, reason)| JobResult::skipped(step, reason))
+                .chain(
+                    groups
+                        .iter()
+                        .flat_map(|g| g.steps.keys())
+                        .map(|step| JobResult::skipped(step, SkipReason::NoFiles)),
+                )
+                .collect_vec();
+            self.write_reports(&opts, &timings, &results, start);
             return Ok(());
         }
-        let hook_ctx = Arc::new(HookContext::new(
+        if opts.reporter != Reporter::Default {
+            clx::progress::set_output(ProgressOutput::Text);
+        }
+        let reporter = opts.reporter;
+        let cache = !opts.no_cache;
+        let mut hook_ctx = HookContext::new(
             files,
             repo.clone(),
             groups,
-            opts.tctx,
+            opts.tctx.clone(),
             run_type,
             hk_progress,
-        ));
+            timings,
+        );
+        hook_ctx.reporter = reporter;
+        hook_ctx.cache = cache;
+        hook_ctx.staged = staged;
+        hook_ctx.check_staged = check_staged;
+        let hook_ctx = Arc::new(hook_ctx);
+        let timings = &hook_ctx.timings;
+        for (step, reason) in skipped {
+            hook_ctx.results.add(JobResult::skipped(&step, reason));
+        }
+
+        // before stashing since only popping the stash restores intent-to-add files
+        if hook_ctx.groups.is_empty() {
+            info!("no steps to run");
+            self.write_reports(&opts, timings, &hook_ctx.results.all(), start);
+            return Ok(());
+        }
 
         watch_for_ctrl_c(hook_ctx.failed.clone());
 
         if stash_method != StashMethod::None {
-            let git_status = git_status
-                .get_or_try_init(async || repo.lock().await.status(None))
-                .await?;
+            let git_status = fetch_git_status(&git_status, &repo, timings).await?;
+            let stash_start = Instant::now();
             repo.lock()
                 .await
                 .stash_unstaged(&file_progress, stash_method, git_status)?;
+            timings.record(TimingKind::Stash, "stash", stash_start);
         }
 
-        if hook_ctx.groups.is_empty() {
-            info!("no steps to run");
-            return Ok(());
-        }
         let mut result = Ok(());
         let multiple_groups = hook_ctx.groups.len() > 1;
         for (i, group) in hook_ctx.groups.iter().enumerate() {
@@ -298,11 +529,23 @@ impl Hook {
                     ctx = ctx.with_progress(group.build_group_progress(name));
                 }
             }
+            let group_start = Instant::now();
             result = result.and(group.run(ctx).await);
+            let name = group.name.clone().unwrap_or_else(|| (i + 1).to_string());
+            timings.record(TimingKind::Group, format!("group {name}"), group_start);
             if settings.fail_fast && result.is_err() {
                 break;
             }
         }
+        if hook_ctx.summary.print() {
+            result = result.map_err(|source| {
+                Error::HookFailed {
+                    hook: self.name.clone(),
+                    source,
+                }
+                .into()
+            });
+        }
         if let Some(hk_progress) = hook_ctx.hk_progress.as_ref() {
             if result.is_ok() {
                 hk_progress.set_status(ProgressStatus::Done);
@@ -311,6 +554,7 @@ impl Hook {
             }
         }
 
+        let pop_stash_start = Instant::now();
         if let Err(err) = repo.lock().await.pop_stash() {
             if result.is_ok() {
                 result = Err(err);
@@ -318,9 +562,49 @@ impl Hook {
                 warn!("Failed to pop stash: {err}");
             }
         }
+        if stash_method != StashMethod::None {
+            timings.record(TimingKind::PopStash, "pop stash", pop_stash_start);
+        }
+        self.write_reports(&opts, timings, &hook_ctx.results.all(), start);
         result
     }
 
+    /// Prints the timings and writes every report asked for, also when the hook exits early.
+    /// Failing to write one is only a warning so the hook's own result isn't lost.
+    fn write_reports(
+        &self,
+        opts: &HookOptions,
+        timings: &Timings,
+        results: &[JobResult],
+        start: Instant,
+    ) {
+        timings.record(TimingKind::Hook, &self.name, start);
+        if opts.timings {
+            timings.print();
+        }
+        if let Some(path) = &opts.timings_json {
+            if let Err(err) = timings.write_json(path) {
+                warn!("failed to write timings to {}: {err:#}", path.display());
+            }
+        }
+        if let Some(path) = &opts.junit {
+            if let Err(err) = junit::write(path, &self.name, results, start.elapsed()) {
+                warn!(
+                    "failed to write junit report to {}: {err:#}",
+                    path.display()
+                );
+            }
+        }
+        if let Some(path) = &opts.sarif {
+            if let Err(err) = sarif::write(path, results) {
+                warn!(
+                    "failed to write sarif report to {}: {err:#}",
+                    path.display()
+                );
+            }
+        }
+    }
+
     async fn file_list(
         &self,
         opts: &HookOptions,
@@ -328,6 +612,7 @@ impl Hook {
         git_status: &OnceCell<GitStatus>,
         stash_method: StashMethod,
         file_progress: &ProgressJob,
+        timings: &Timings,
     ) -> Result<BTreeSet<PathBuf>> {
         const EMPTY_REF: &str = "0000000000000000000000000000000000000000";
         let stash = stash_method != StashMethod::None;
@@ -349,9 +634,7 @@ impl Hook {
             let pathspec = glob.iter().map(OsString::from).collect::<Vec<_>>();
             let mut all_files = repo.lock().await.all_files(Some(&pathspec))?;
             if !stash {
-                let git_status = git_status
-                    .get_or_try_init(async || repo.lock().await.status(None))
-                    .await?;
+                let git_status = fetch_git_status(git_status, &repo, timings).await?;
                 all_files.extend(git_status.untracked_files.iter().cloned());
             }
             let all_files = all_files.into_iter().collect_vec();
@@ -379,23 +662,17 @@ impl Hook {
             file_progress.prop("message", "Fetching all files in repo");
             let mut all_files = repo.lock().await.all_files(None)?;
             if !stash {
-                let git_status = git_status
-                    .get_or_try_init(async || repo.lock().await.status(None))
-                    .await?;
+                let git_status = fetch_git_status(git_status, &repo, timings).await?;
                 all_files.extend(git_status.untracked_files.iter().cloned());
             }
             all_files
         } else if stash {
             file_progress.prop("message", "Fetching staged files");
-            let git_status = git_status
-                .get_or_try_init(async || repo.lock().await.status(None))
-                .await?;
+            let git_status = fetch_git_status(git_status, &repo, timings).await?;
             git_status.staged_files.iter().cloned().collect()
         } else {
             file_progress.prop("message", "Fetching modified files");
-            let git_status = git_status
-                .get_or_try_init(async || repo.lock().await.status(None))
-                .await?;
+            let git_status = fetch_git_status(git_status, &repo, timings).await?;
             git_status
                 .staged_files
                 .iter()
@@ -456,6 +733,23 @@ impl Hook {
     }
 }
 
+/// Changes stashed by an hk process which was killed before it could restore them
+fn warn_leftover_stashes(root: &Path) {
+    match StashJournal::leftover(root) {
+        Ok(leftover) if !leftover.is_empty() => warn!(
+            "{} stash{} left by an interrupted hk run, see `hk stash list` and `hk stash restore`",
+            leftover.len(),
+            if leftover.len() == 1 {
+                " was"
+            } else {
+                "es were"
+            }
+        ),
+        Ok(_) => {}
+        Err(err) => debug!("failed to read stash journal: {err:?}"),
+    }
+}
+
 fn watch_for_ctrl_c(cancel: CancellationToken) {
     tokio::spawn(async move {
         if let Err(err) = signal::ctrl_c().await {
@@ -491,3 +785,18 @@ fn can_exit_early(groups: &[StepGroup], files: &BTreeSet<PathBuf>, run_type: Run
         })
     })
 }
+
+async fn fetch_git_status<'a>(
+    git_status: &'a OnceCell<GitStatus>,
+    repo: &Mutex<Git>,
+    timings: &Timings,
+) -> Result<&'a GitStatus> {
+    git_status
+        .get_or_try_init(async || {
+            let start = Instant::now();
+            let status = repo.lock().await.status(None);
+            timings.record(TimingKind::GitStatus, "git status", start);
+            status
+        })
+        .await
+}
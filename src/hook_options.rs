@@ -1,4 +1,6 @@
-use crate::{Result, config::Config, tera::Context};
+use std::path::PathBuf;
+
+use crate::{Result, config::Config, reporter::Reporter, tera::Context};
 
 #[derive(clap::Args)]
 pub(crate) struct HookOptions {
@@ -33,6 +35,28 @@ pub(crate) struct HookOptions {
     /// Print the plan instead of running the hook
     #[clap(short = 'P', long)]
     pub plan: bool,
+    /// Print the plan as JSON
+    #[clap(long, requires = "plan")]
+    pub json: bool,
+    /// Print how long each part of the hook took, slowest first
+    #[clap(long)]
+    pub timings: bool,
+    /// Write the timings as JSON to this file
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    pub timings_json: Option<PathBuf>,
+    /// Write a JUnit XML report with a testcase for each step to this file
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    pub junit: Option<PathBuf>,
+    /// Write diagnostics from steps with an `output_parser` to this file as SARIF
+    #[clap(long, value_hint = clap::ValueHint::FilePath)]
+    pub sarif: Option<PathBuf>,
+    /// How to show progress and results
+    /// `github` groups each step's output and emits annotations for GitHub Actions
+    #[clap(long, value_enum, default_value_t)]
+    pub reporter: Reporter,
+    /// Run every check even on files that already passed it
+    #[clap(long)]
+    pub no_cache: bool,
     /// Run specific step(s)
     #[clap(short = 'S', long)]
     pub step: Vec<String>,
@@ -0,0 +1,268 @@
+//! Runs a step job's command and kills every process it started if it times out.
+//!
+//! Killing only the shell that runs a step would leave its children running (and holding
+//! stdout open) so jobs with a timeout are started in their own process group and the whole
+//! group is killed. This also catches children which clear their env or are backgrounded with
+//! `&`.
+
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
+
+use clx::progress::ProgressJob;
+use ensembler::{CmdLineRunner, CmdResult};
+use tokio_util::sync::CancellationToken;
+
+pub struct JobCmd {
+    program: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    dir: Option<PathBuf>,
+    pr: Option<Arc<ProgressJob>>,
+    interactive: bool,
+    cancel: CancellationToken,
+}
+
+impl JobCmd {
+    pub fn new<P: AsRef<OsStr>>(program: P) -> Self {
+        Self {
+            program: program.as_ref().to_string_lossy().to_string(),
+            args: vec![],
+            env: vec![],
+            dir: None,
+            pr: None,
+            interactive: false,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    pub fn arg<S: AsRef<OsStr>>(mut self, arg: S) -> Self {
+        self.args.push(arg.as_ref().to_string_lossy().to_string());
+        self
+    }
+
+    pub fn env<K: Into<String>, V: Into<String>>(mut self, key: K, val: V) -> Self {
+        self.env.push((key.into(), val.into()));
+        self
+    }
+
+    pub fn current_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn with_pr(mut self, pr: Arc<ProgressJob>) -> Self {
+        self.pr = Some(pr);
+        self
+    }
+
+    pub fn with_cancel_token(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Connects the command to the terminal
+    pub fn interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    /// Runs the command, returns `None` if it was killed because `timeout` elapsed
+    pub async fn execute(self, timeout: Option<Duration>) -> Option<ensembler::Result<CmdResult>> {
+        match timeout {
+            // interactive commands stay in hk's process group so they can read from the terminal
+            #[cfg(unix)]
+            Some(timeout) if !self.interactive => self.execute_in_process_group(timeout).await,
+            Some(timeout) => {
+                let cancel = self.cancel.clone();
+                let exec = self.runner().execute();
+                tokio::pin!(exec);
+                tokio::select! {
+                    result = &mut exec => Some(result),
+                    _ = tokio::time::sleep(timeout) => {
+                        // only the job's shell is killed here
+                        cancel.cancel();
+                        let _ = exec.await;
+                        None
+                    }
+                }
+            }
+            None => Some(self.runner().execute().await),
+        }
+    }
+
+    fn runner(self) -> CmdLineRunner {
+        let mut cmd = CmdLineRunner::new(&self.program)
+            .args(&self.args)
+            .with_cancel_token(self.cancel)
+            .show_stderr_on_error(false);
+        for (key, val) in self.env {
+            cmd = cmd.env(key, val);
+        }
+        if let Some(dir) = self.dir {
+            cmd = cmd.current_dir(dir);
+        }
+        if let Some(pr) = self.pr {
+            cmd = cmd.with_pr(pr);
+        }
+        if self.interactive {
+            cmd = cmd
+                .stdin(Stdio::inherit())
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::inherit());
+        }
+        cmd
+    }
+
+    /// Like `CmdLineRunner::execute` but the command leads a new process group which is killed
+    /// when `timeout` elapses or the job is cancelled
+    #[cfg(unix)]
+    async fn execute_in_process_group(
+        self,
+        timeout: Duration,
+    ) -> Option<ensembler::Result<CmdResult>> {
+        use clx::progress::ProgressStatus;
+        use tokio::io::{AsyncBufReadExt, BufReader};
+        use tokio::sync::Mutex;
+
+        debug!("$ {self}");
+        let mut cmd = tokio::process::Command::new(&self.program);
+        cmd.args(&self.args)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0);
+        if let Some(dir) = &self.dir {
+            cmd.current_dir(dir);
+        }
+        let mut cp = match cmd.spawn() {
+            Ok(cp) => cp,
+            Err(err) => return Some(Err(err.into())),
+        };
+        let pgid = cp.id();
+        if let Some(pr) = &self.pr {
+            pr.prop("ensembler_cmd", &self.to_string());
+            pr.prop("ensembler_stdout", &"".to_string());
+            pr.set_status(ProgressStatus::Running);
+        }
+        let result = Arc::new(Mutex::new(CmdResult::default()));
+        let combined_output = Arc::new(Mutex::new(Vec::new()));
+        let mut readers = tokio::task::JoinSet::new();
+        if let Some(stdout) = cp.stdout.take() {
+            let result = result.clone();
+            let combined_output = combined_output.clone();
+            let pr = self.pr.clone();
+            readers.spawn(async move {
+                let mut lines = BufReader::new(stdout).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let mut result = result.lock().await;
+                    result.stdout += &line;
+                    result.stdout += "\n";
+                    result.combined_output += &line;
+                    result.combined_output += "\n";
+                    if let Some(pr) = &pr {
+                        pr.prop("ensembler_stdout", &line);
+                        pr.update();
+                    }
+                    combined_output.lock().await.push(line);
+                }
+            });
+        }
+        if let Some(stderr) = cp.stderr.take() {
+            let result = result.clone();
+            let combined_output = combined_output.clone();
+            let pr = self.pr.clone();
+            readers.spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let mut result = result.lock().await;
+                    result.stderr += &line;
+                    result.stderr += "\n";
+                    result.combined_output += &line;
+                    result.combined_output += "\n";
+                    if let Some(pr) = &pr {
+                        pr.println(&line);
+                    }
+                    combined_output.lock().await.push(line);
+                }
+            });
+        }
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+        let status = tokio::select! {
+            status = cp.wait() => status,
+            _ = &mut deadline => {
+                kill_process_group(pgid);
+                let _ = cp.wait().await;
+                return None;
+            }
+            _ = self.cancel.cancelled() => {
+                kill_process_group(pgid);
+                cp.wait().await
+            }
+        };
+        let status = match status {
+            Ok(status) => status,
+            Err(err) => return Some(Err(err.into())),
+        };
+        // processes the command started in the background may still be writing output
+        tokio::select! {
+            _ = readers.join_all() => {}
+            _ = &mut deadline => {
+                kill_process_group(pgid);
+                return None;
+            }
+        }
+        let mut result = result.lock().await.to_owned();
+        result.status = status;
+        if status.success() {
+            if let Some(pr) = &self.pr {
+                pr.set_status(ProgressStatus::Done);
+            }
+            return Some(Ok(result));
+        }
+        if let Some(pr) = &self.pr {
+            pr.set_status(ProgressStatus::Failed);
+        }
+        let output = combined_output.lock().await.join("\n").trim().to_string();
+        Some(Err(ensembler::Error::ScriptFailed(Box::new((
+            self.program.clone(),
+            self.args.clone(),
+            output,
+            result,
+        )))))
+    }
+}
+
+/// Sends SIGKILL to every process in the group led by `pgid`
+#[cfg(unix)]
+fn kill_process_group(pgid: Option<u32>) {
+    use nix::sys::signal::{Signal, killpg};
+    use nix::unistd::Pid;
+
+    let Some(pgid) = pgid else {
+        return;
+    };
+    debug!("killing process group {pgid}");
+    // the group may have exited already
+    if let Err(err) = killpg(Pid::from_raw(pgid as i32), Signal::SIGKILL) {
+        debug!("failed to kill process group {pgid}: {err}");
+    }
+}
+
+impl std::fmt::Display for JobCmd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cmd = format!("{} {}", self.program, self.args.join(" "));
+        // the same as `CmdLineRunner`
+        write!(
+            f,
+            "{}",
+            cmd.strip_prefix("sh -o errexit -c ").unwrap_or(&cmd)
+        )
+    }
+}
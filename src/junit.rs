@@ -0,0 +1,87 @@
+use std::{fmt::Write, path::Path, time::Duration};
+
+use crate::{
+    Result,
+    results::{JobResult, Outcome},
+};
+
+/// Writes a JUnit XML report with one testsuite for the hook and one testcase per job
+pub fn write(path: &Path, hook: &str, results: &[JobResult], duration: Duration) -> Result<()> {
+    let count = |f: fn(&Outcome) -> bool| results.iter().filter(|r| f(&r.outcome)).count();
+    let failures = count(|o| matches!(o, Outcome::Failed(_)));
+    let skipped = count(|o| matches!(o, Outcome::Skipped(_)));
+    let time = duration.as_secs_f64();
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        xml,
+        r#"<testsuites name="hk" tests="{}" failures="{failures}" skipped="{skipped}" time="{time:.3}">"#,
+        results.len(),
+    )?;
+    writeln!(
+        xml,
+        r#"  <testsuite name="{}" tests="{}" failures="{failures}" errors="0" skipped="{skipped}" time="{time:.3}">"#,
+        escape(hook),
+        results.len(),
+    )?;
+    for result in results {
+        write!(
+            xml,
+            r#"    <testcase name="{}" classname="{}" time="{:.3}">"#,
+            escape(&result.name()),
+            escape(&format!("{hook}.{}", result.step)),
+            result.duration.as_secs_f64(),
+        )?;
+        match &result.outcome {
+            Outcome::Passed => {}
+            Outcome::Warned(message) => {
+                write!(
+                    xml,
+                    r#"<properties><property name="warning" value="{}"/></properties>"#,
+                    escape(message)
+                )?;
+            }
+            Outcome::Failed(message) => {
+                write!(
+                    xml,
+                    r#"<failure message="{}">{}</failure>"#,
+                    escape(message),
+                    escape(result.command.as_deref().unwrap_or_default())
+                )?;
+            }
+            Outcome::Skipped(reason) => {
+                write!(xml, r#"<skipped message="{}"/>"#, escape(reason))?;
+            }
+        }
+        if !result.stdout.is_empty() {
+            write!(xml, "<system-out>{}</system-out>", escape(&result.stdout))?;
+        }
+        if !result.stderr.is_empty() {
+            write!(xml, "<system-err>{}</system-err>", escape(&result.stderr))?;
+        }
+        writeln!(xml, "</testcase>")?;
+    }
+    writeln!(xml, "  </testsuite>")?;
+    writeln!(xml, "</testsuites>")?;
+    xx::file::write(path, xml)?;
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    let s = console::strip_ansi_codes(s);
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // other control characters aren't allowed in XML 1.0
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -8,23 +8,38 @@ pub use eyre::Result;
 mod cache;
 mod cli;
 mod config;
+mod diagnostics;
 mod env;
 mod error;
 mod file_rw_locks;
+mod file_types;
 mod git;
 mod glob;
 mod hash;
 mod hook;
 mod hook_options;
+mod job_processes;
+mod junit;
 mod logger;
+mod pkl;
+mod plan;
+mod profile;
+mod reporter;
+mod result_cache;
+mod results;
+mod sarif;
 mod settings;
+mod staged_files;
+mod stash_journal;
 mod step;
 mod step_context;
 mod step_depends;
 mod step_group;
 mod step_job;
 mod step_locks;
+mod summary;
 mod tera;
+mod timings;
 mod ui;
 mod version;
 
@@ -49,9 +64,13 @@ async fn main() -> Result<()> {
 }
 
 fn friendly_error(e: eyre::Report) -> Result<()> {
-    if let Some(ensembler::Error::ScriptFailed(err)) =
-        e.chain().find_map(|e| e.downcast_ref::<ensembler::Error>())
-    {
+    if let Some(err) = error::script_failed(&e) {
+        // the failed commands have already been shown in the hook's summary
+        if e.chain()
+            .any(|e| matches!(e.downcast_ref(), Some(error::Error::HookFailed { .. })))
+        {
+            std::process::exit(err.3.status.code().unwrap_or(1));
+        }
         handle_script_failed(&err.0, &err.1, &err.2, &err.3);
     }
     Err(e)
@@ -59,10 +78,7 @@ fn friendly_error(e: eyre::Report) -> Result<()> {
 
 fn handle_script_failed(bin: &str, args: &[String], output: &str, result: &ensembler::CmdResult) {
     clx::progress::flush();
-    let mut cmd = format!("{} {}", bin, args.join(" "));
-    if cmd.starts_with("sh -o errexit -c ") {
-        cmd = cmd[17..].to_string();
-    }
+    let cmd = summary::command(bin, args);
     eprintln!("{}\n{output}", style::ered(format!("Error running {cmd}")));
     if let Err(e) = write_output_file(result) {
         eprintln!("Error writing output file: {e:?}");
@@ -0,0 +1,205 @@
+//! Records what a pkl config depends on so cached configs can be invalidated when any of it
+//! changes.
+//!
+//! When a config is evaluated through `pkl server` hk serves its local modules itself (see
+//! `server`) so every module pkl loads is recorded, including ones found by glob imports and the
+//! modules they amend. Files and env vars read with `read()` are found by scanning those modules
+//! and a config which reads a uri that isn't a literal string is always evaluated again. When the
+//! server can't be used, modules are found by scanning for `amends`, `extends` and `import` too.
+//!
+//! Packages and https modules are versioned so they can't change under us.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::SystemTime,
+};
+
+use globset::GlobBuilder;
+use indexmap::IndexMap;
+use regex::Regex;
+
+static IMPORT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\b(?:amends|extends|import\*?)\s+"([^"]+)""#).unwrap());
+static READ_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bread[?*]?\(\s*"([^"]+)"\s*\)"#).unwrap());
+static READ_CALL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bread[?*]?\(").unwrap());
+
+/// Files and environment variables a pkl module read while it was evaluated
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    /// modules and files with their mtime when they were read, including the directories
+    /// searched by globs
+    pub files: IndexMap<PathBuf, Option<SystemTime>>,
+    /// env vars with their value when they were read
+    pub env: IndexMap<String, Option<String>>,
+    /// the config reads something which is only known once it is evaluated
+    pub dynamic: bool,
+}
+
+impl Dependencies {
+    /// Returns false if the file was already recorded
+    pub fn add_file(&mut self, path: &Path) -> bool {
+        if self.files.contains_key(path) {
+            return false;
+        }
+        let mtime = path.metadata().and_then(|m| m.modified()).ok();
+        self.files.insert(path.to_path_buf(), mtime);
+        true
+    }
+
+    fn add_env(&mut self, name: &str) {
+        if !self.env.contains_key(name) {
+            self.env.insert(name.to_string(), std::env::var(name).ok());
+        }
+    }
+
+    /// Adds the files and env vars the module at `path` reads with `read()`
+    pub fn add_reads(&mut self, path: &Path, source: &str) {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut literal = 0;
+        for cap in READ_RE.captures_iter(source) {
+            literal += 1;
+            if cap[1].contains("\\(") {
+                // string interpolation
+                self.dynamic = true;
+            } else if let Some(var) = cap[1].strip_prefix("env:") {
+                self.add_env(var);
+            } else {
+                for file in resolve(self, dir, &cap[1]) {
+                    self.add_file(&file);
+                }
+            }
+        }
+        if READ_CALL_RE.find_iter(source).count() > literal {
+            self.dynamic = true;
+        }
+    }
+}
+
+/// Finds a module's dependencies by scanning it and the local modules it imports
+pub fn scan(path: &Path) -> Dependencies {
+    let mut deps = Dependencies::default();
+    add_module(&mut deps, path);
+    deps
+}
+
+fn add_module(deps: &mut Dependencies, path: &Path) {
+    if !deps.add_file(path) {
+        return;
+    }
+    // a missing module is still tracked so creating it invalidates the cache
+    let Ok(source) = xx::file::read_to_string(path) else {
+        return;
+    };
+    let dir = path.parent().unwrap_or(Path::new("."));
+    for cap in IMPORT_RE.captures_iter(&source) {
+        for module in resolve(deps, dir, &cap[1]) {
+            add_module(deps, &module);
+        }
+    }
+    deps.add_reads(path, &source);
+}
+
+/// Turns a module or resource uri into local files, expanding globs
+fn resolve(deps: &mut Dependencies, dir: &Path, uri: &str) -> Vec<PathBuf> {
+    let path = match uri.strip_prefix("file://") {
+        Some(path) => PathBuf::from(path),
+        None if uri.contains(':') => return vec![],
+        None => dir.join(uri),
+    };
+    let path_str = path.to_string_lossy();
+    if !path_str.contains(['*', '?', '[', '{']) {
+        return vec![path];
+    }
+    // the deepest directory without any glob characters, adding or removing files changes its mtime
+    let base = path
+        .ancestors()
+        .skip(1)
+        .find(|p| !p.to_string_lossy().contains(['*', '?', '[', '{']))
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    let Ok(glob) = GlobBuilder::new(&path_str).literal_separator(true).build() else {
+        return vec![];
+    };
+    let glob = glob.compile_matcher();
+    let mut files = vec![];
+    let mut dirs = vec![base];
+    while let Some(dir) = dirs.pop() {
+        deps.add_file(&dir);
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if glob.is_match(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn write(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join("hk-pkl").join(name);
+        xx::file::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_scan() {
+        let config = write(
+            "test_dependencies/hk.pkl",
+            r#"amends "package://github.com/jdx/hk/releases/download/v0.8.0/hk@0.8.0#/Config.pkl"
+import "linters.pkl"
+import* "steps/*.pkl" as steps
+local token = read?("env:HK_TEST_TOKEN")
+"#,
+        );
+        let linters = write(
+            "test_dependencies/linters.pkl",
+            r#"import "hk.pkl"
+local ignore = read("file:///tmp/hk-pkl-ignore")
+"#,
+        );
+        let step = write("test_dependencies/steps/a.pkl", "");
+        let dir = config.parent().unwrap();
+        let deps = scan(&config);
+        assert_eq!(
+            deps.files.keys().cloned().collect::<BTreeSet<_>>(),
+            BTreeSet::from([
+                config.clone(),
+                linters,
+                step,
+                dir.join("steps"),
+                PathBuf::from("/tmp/hk-pkl-ignore"),
+            ])
+        );
+        assert_eq!(deps.env.keys().collect::<Vec<_>>(), vec!["HK_TEST_TOKEN"]);
+        assert!(!deps.dynamic);
+    }
+
+    #[test]
+    fn test_add_reads_dynamic() {
+        let mut deps = Dependencies::default();
+        deps.add_reads(
+            Path::new("hk.pkl"),
+            r#"local name = "x"
+local a = read("env:\(name)")
+"#,
+        );
+        assert!(deps.dynamic);
+
+        let mut deps = Dependencies::default();
+        deps.add_reads(Path::new("hk.pkl"), "local a = read(\"env:\" + name)");
+        assert!(deps.dynamic);
+    }
+}
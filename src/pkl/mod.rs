@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use eyre::{WrapErr, bail};
+use serde::de::DeserializeOwned;
+
+use crate::{Result, env, error::Error};
+
+mod deps;
+mod server;
+
+pub use deps::Dependencies;
+
+/// Evaluates a pkl module and deserializes its JSON output along with what it depends on
+///
+/// This uses a `pkl server` process and falls back to running `pkl eval` if the server can't be used.
+pub fn eval<T: DeserializeOwned>(path: &Path) -> Result<(T, Dependencies)> {
+    if !*env::HK_PKL_SERVER {
+        return eval_cli(path);
+    }
+    // `pkl server` doesn't resolve PklProject dependencies on its own
+    if path
+        .ancestors()
+        .skip(1)
+        .any(|dir| dir.join("PklProject").exists())
+    {
+        return eval_cli(path);
+    }
+    let (json, deps) = match server::eval(path) {
+        Ok(out) => out,
+        Err(err) if err.downcast_ref::<Error>().is_some() => return Err(err),
+        Err(err) => {
+            debug!("pkl server failed, falling back to pkl eval: {err:#}");
+            return eval_cli(path);
+        }
+    };
+    let deps = deps.unwrap_or_else(|| deps::scan(path));
+    let config = serde_json::from_str(&json).wrap_err("failed to parse pkl config file")?;
+    Ok((config, deps))
+}
+
+fn eval_cli<T: DeserializeOwned>(path: &Path) -> Result<(T, Dependencies)> {
+    // scanned before evaluating so changes made while pkl runs invalidate the cache
+    let deps = deps::scan(path);
+    let config = match parse_pkl("pkl", path) {
+        Ok(raw) => Ok(raw),
+        Err(err) => {
+            // if pkl bin is not installed
+            if which::which("pkl").is_err() {
+                if let Ok(out) = parse_pkl("mise x -- pkl", path) {
+                    return Ok((out, deps));
+                };
+                bail!("install pkl cli to use pkl config files https://pkl-lang.org/");
+            } else {
+                Err(err).wrap_err("failed to read pkl config file")
+            }
+        }
+    }?;
+    Ok((config, deps))
+}
+
+fn parse_pkl<T: DeserializeOwned>(bin: &str, path: &Path) -> Result<T> {
+    let json = xx::process::sh(&format!("{bin} eval -f json {}", path.display()))?;
+    serde_json::from_str(&json).wrap_err("failed to parse pkl config file")
+}
@@ -0,0 +1,472 @@
+//! Evaluates pkl modules with a `pkl server` process which is started once and reused for every
+//! config file hk reads
+//!
+//! Local modules are read by hk instead of pkl so the modules a config depends on can be recorded
+//! (see `Dependencies`). Each config gets its own evaluator since pkl caches the modules an
+//! evaluator has read.
+//!
+//! https://pkl-lang.org/main/current/bindings-specification/message-passing-api.html
+
+use std::{
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{LazyLock, Mutex},
+};
+
+use eyre::{bail, eyre};
+use indexmap::IndexMap;
+use regex::Regex;
+use rmpv::Value;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use super::Dependencies;
+use crate::{Result, error::Error};
+
+const CREATE_EVALUATOR_REQUEST: i64 = 0x20;
+const CREATE_EVALUATOR_RESPONSE: i64 = 0x21;
+const CLOSE_EVALUATOR: i64 = 0x22;
+const EVALUATE_REQUEST: i64 = 0x23;
+const EVALUATE_RESPONSE: i64 = 0x24;
+const LOG: i64 = 0x25;
+const READ_MODULE_REQUEST: i64 = 0x28;
+const READ_MODULE_RESPONSE: i64 = 0x29;
+const LIST_MODULES_REQUEST: i64 = 0x2c;
+const LIST_MODULES_RESPONSE: i64 = 0x2d;
+
+/// the same defaults as `pkl eval`
+const ALLOWED_MODULES: &[&str] = &[
+    "pkl:",
+    "repl:",
+    "file:",
+    "modulepath:",
+    "https:",
+    "package:",
+    "projectpackage:",
+];
+const ALLOWED_RESOURCES: &[&str] = &[
+    "env:",
+    "prop:",
+    "file:",
+    "modulepath:",
+    "https:",
+    "package:",
+    "projectpackage:",
+];
+
+static SERVER: Mutex<Option<Server>> = Mutex::new(None);
+
+static LOCATION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\(file://([^,)]+), line (\d+)\)").unwrap());
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateEvaluatorRequest {
+    request_id: i64,
+    allowed_modules: &'static [&'static str],
+    allowed_resources: &'static [&'static str],
+    env: IndexMap<String, String>,
+    /// older versions of pkl only pick up the output format from this property
+    properties: IndexMap<&'static str, &'static str>,
+    output_format: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    client_module_readers: Vec<ModuleReader>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ModuleReader {
+    scheme: &'static str,
+    has_hierarchical_uris: bool,
+    is_globbable: bool,
+    is_local: bool,
+}
+
+/// Replaces pkl's own reader for local modules
+const FILE_READER: ModuleReader = ModuleReader {
+    scheme: "file",
+    has_hierarchical_uris: true,
+    is_globbable: true,
+    is_local: true,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateEvaluatorResponse {
+    evaluator_id: Option<i64>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EvaluateRequest {
+    request_id: i64,
+    evaluator_id: i64,
+    module_uri: String,
+    expr: &'static str,
+}
+
+#[derive(Deserialize)]
+struct EvaluateResponse {
+    /// the value in pkl's binary encoding
+    result: Option<Value>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CloseEvaluator {
+    evaluator_id: i64,
+}
+
+/// `ReadModuleRequest` and `ListModulesRequest`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadRequest {
+    request_id: i64,
+    evaluator_id: i64,
+    uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadModuleResponse {
+    request_id: i64,
+    evaluator_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contents: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ListModulesResponse {
+    request_id: i64,
+    evaluator_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_elements: Option<Vec<PathElement>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PathElement {
+    name: String,
+    is_directory: bool,
+}
+
+#[derive(Deserialize)]
+struct Log {
+    level: i64,
+    message: String,
+}
+
+/// Renders a module's output as JSON along with what it depends on, the dependencies are `None`
+/// if pkl read the module itself instead of asking hk
+pub fn eval(path: &Path) -> Result<(String, Option<Dependencies>)> {
+    let mut server = SERVER.lock().unwrap();
+    if server.is_none() {
+        *server = Some(Server::start()?);
+    }
+    let result = server.as_mut().unwrap().evaluate(path);
+    if let Err(err) = &result {
+        // the server may be in a bad state after anything but an evaluation error
+        if err.downcast_ref::<Error>().is_none() {
+            *server = None;
+        }
+    }
+    result
+}
+
+struct Server {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    request_id: i64,
+    /// false if the server rejected hk's reader for local modules
+    read_modules: bool,
+    /// what the module being evaluated has read so far
+    deps: Dependencies,
+}
+
+impl Server {
+    fn start() -> Result<Self> {
+        let mut cmd = if which::which("pkl").is_ok() {
+            Command::new("pkl")
+        } else if which::which("mise").is_ok() {
+            let mut cmd = Command::new("mise");
+            cmd.args(["x", "--", "pkl"]);
+            cmd
+        } else {
+            bail!("install pkl cli to use pkl config files https://pkl-lang.org/");
+        };
+        debug!("starting pkl server");
+        let mut child = cmd
+            .arg("server")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        Ok(Self {
+            stdin: BufWriter::new(child.stdin.take().unwrap()),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            request_id: 0,
+            read_modules: true,
+            deps: Dependencies::default(),
+        })
+    }
+
+    fn create_evaluator(&mut self) -> Result<i64> {
+        let request_id = self.next_request_id();
+        self.send(
+            CREATE_EVALUATOR_REQUEST,
+            &CreateEvaluatorRequest {
+                request_id,
+                allowed_modules: ALLOWED_MODULES,
+                allowed_resources: ALLOWED_RESOURCES,
+                env: std::env::vars().collect(),
+                properties: IndexMap::from([("pkl.outputFormat", "json")]),
+                output_format: "json",
+                client_module_readers: match self.read_modules {
+                    true => vec![FILE_READER],
+                    false => vec![],
+                },
+            },
+        )?;
+        let response: CreateEvaluatorResponse = self.receive(CREATE_EVALUATOR_RESPONSE)?;
+        if let Some(error) = response.error {
+            if self.read_modules {
+                debug!("pkl server rejected the module reader: {error}");
+                self.read_modules = false;
+                return self.create_evaluator();
+            }
+            bail!("failed to create pkl evaluator: {error}");
+        }
+        response
+            .evaluator_id
+            .ok_or_else(|| eyre!("pkl server did not return an evaluatorId"))
+    }
+
+    fn evaluate(&mut self, path: &Path) -> Result<(String, Option<Dependencies>)> {
+        debug!("evaluating {} with pkl server", path.display());
+        let path = path.canonicalize()?;
+        let evaluator_id = self.create_evaluator()?;
+        self.deps = Dependencies::default();
+        let result = self.evaluate_module(evaluator_id, &path);
+        self.send(CLOSE_EVALUATOR, &CloseEvaluator { evaluator_id })?;
+        let deps = std::mem::take(&mut self.deps);
+        let json = result?;
+        if !deps.files.contains_key(&path) {
+            debug!("pkl read {} without hk's module reader", path.display());
+            return Ok((json, None));
+        }
+        Ok((json, Some(deps)))
+    }
+
+    fn evaluate_module(&mut self, evaluator_id: i64, path: &Path) -> Result<String> {
+        let module_uri = url::Url::from_file_path(path)
+            .map_err(|_| eyre!("invalid module path: {}", path.display()))?;
+        let request_id = self.next_request_id();
+        self.send(
+            EVALUATE_REQUEST,
+            &EvaluateRequest {
+                request_id,
+                evaluator_id,
+                module_uri: module_uri.to_string(),
+                expr: "output.text",
+            },
+        )?;
+        let response: EvaluateResponse = self.receive(EVALUATE_RESPONSE)?;
+        if let Some(error) = response.error {
+            return Err(evaluation_error(&error).into());
+        }
+        let Some(Value::Binary(result)) = response.result else {
+            bail!("pkl server did not return a result");
+        };
+        // a String in pkl's binary encoding is a msgpack string
+        Ok(rmp_serde::from_slice(&result)?)
+    }
+
+    fn next_request_id(&mut self) -> i64 {
+        self.request_id += 1;
+        self.request_id
+    }
+
+    fn send(&mut self, code: i64, body: &impl Serialize) -> Result<()> {
+        rmp_serde::encode::write_named(&mut self.stdin, &(code, body))?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Waits for a message with `code`, logging any log messages and serving any module reads
+    /// received in the meantime
+    fn receive<T: DeserializeOwned>(&mut self, code: i64) -> Result<T> {
+        loop {
+            let (c, body) = read_message(&mut self.stdout)?;
+            match c {
+                c if c == code => return Ok(rmpv::ext::from_value(body)?),
+                LOG => {
+                    let log: Log = rmpv::ext::from_value(body)?;
+                    match log.level {
+                        0 => trace!("pkl: {}", log.message),
+                        _ => warn!("pkl: {}", log.message),
+                    }
+                }
+                READ_MODULE_REQUEST => {
+                    let request: ReadRequest = rmpv::ext::from_value(body)?;
+                    let result = read_module(&mut self.deps, &request.uri);
+                    self.send(
+                        READ_MODULE_RESPONSE,
+                        &ReadModuleResponse {
+                            request_id: request.request_id,
+                            evaluator_id: request.evaluator_id,
+                            error: result.as_ref().err().map(|e| format!("{e:#}")),
+                            contents: result.ok(),
+                        },
+                    )?;
+                }
+                LIST_MODULES_REQUEST => {
+                    let request: ReadRequest = rmpv::ext::from_value(body)?;
+                    let result = list_modules(&mut self.deps, &request.uri);
+                    self.send(
+                        LIST_MODULES_RESPONSE,
+                        &ListModulesResponse {
+                            request_id: request.request_id,
+                            evaluator_id: request.evaluator_id,
+                            error: result.as_ref().err().map(|e| format!("{e:#}")),
+                            path_elements: result.ok(),
+                        },
+                    )?;
+                }
+                _ => bail!("unexpected message from pkl server: {c:#x} {body}"),
+            }
+        }
+    }
+}
+
+fn file_path(uri: &str) -> Result<PathBuf> {
+    url::Url::parse(uri)?
+        .to_file_path()
+        .map_err(|_| eyre!("not a local file: {uri}"))
+}
+
+/// Reads a local module for pkl, recording it and what it reads
+fn read_module(deps: &mut Dependencies, uri: &str) -> Result<String> {
+    let path = file_path(uri)?;
+    // recorded before reading so changes made while pkl runs invalidate the cache
+    deps.add_file(&path);
+    let source = xx::file::read_to_string(&path)?;
+    deps.add_reads(&path, &source);
+    Ok(source)
+}
+
+/// Lists a directory for a glob import, recording it so adding or removing modules is noticed
+fn list_modules(deps: &mut Dependencies, uri: &str) -> Result<Vec<PathElement>> {
+    let dir = file_path(uri)?;
+    deps.add_file(&dir);
+    let mut elements = vec![];
+    for entry in std::fs::read_dir(&dir)? {
+        let entry = entry?;
+        elements.push(PathElement {
+            name: entry.file_name().to_string_lossy().to_string(),
+            is_directory: entry.file_type()?.is_dir(),
+        });
+    }
+    Ok(elements)
+}
+
+/// Reads a `[code, body]` message
+fn read_message(r: &mut impl std::io::Read) -> Result<(i64, Value)> {
+    let message = rmpv::decode::read_value(r)?;
+    match message {
+        Value::Array(mut message) if message.len() == 2 => {
+            let body = message.pop().unwrap();
+            let code = message[0]
+                .as_i64()
+                .ok_or_else(|| eyre!("unexpected message from pkl server: {}", message[0]))?;
+            Ok((code, body))
+        }
+        message => bail!("unexpected message from pkl server: {message}"),
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Pulls the first source location out of a pkl error message
+fn evaluation_error(error: &str) -> Error {
+    let location = LOCATION_RE
+        .captures(error)
+        .map(|cap| format!("{}:{}", xx::file::display_path(Path::new(&cap[1])), &cap[2]));
+    let message = error
+        .trim()
+        .trim_start_matches("–– Pkl Error ––")
+        .trim()
+        .to_string();
+    Error::PklEval { location, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_message() {
+        let mut buf = vec![];
+        rmp_serde::encode::write_named(
+            &mut buf,
+            &(
+                EVALUATE_RESPONSE,
+                IndexMap::from([("result", Value::Binary(vec![0xa2, b'{', b'}']))]),
+            ),
+        )
+        .unwrap();
+        let (code, body) = read_message(&mut &buf[..]).unwrap();
+        assert_eq!(code, EVALUATE_RESPONSE);
+        let response: EvaluateResponse = rmpv::ext::from_value(body).unwrap();
+        let Some(Value::Binary(result)) = response.result else {
+            panic!("no result");
+        };
+        assert_eq!(rmp_serde::from_slice::<String>(&result).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_read_module() {
+        let dir = std::env::temp_dir().join("hk-pkl").join("test_read_module");
+        let module = dir.join("hk.pkl");
+        xx::file::write(&module, "local token = read?(\"env:HK_TEST_TOKEN\")\n").unwrap();
+        let mut deps = Dependencies::default();
+        let uri = url::Url::from_file_path(&module).unwrap();
+        let source = read_module(&mut deps, uri.as_str()).unwrap();
+        assert!(source.contains("HK_TEST_TOKEN"));
+        let uri = url::Url::from_directory_path(&dir).unwrap();
+        let elements = list_modules(&mut deps, uri.as_str()).unwrap();
+        assert_eq!(elements.len(), 1);
+        assert_eq!(elements[0].name, "hk.pkl");
+        assert_eq!(
+            deps.files.keys().collect::<Vec<_>>(),
+            vec![&module, &dir.join("")]
+        );
+        assert_eq!(deps.env.keys().collect::<Vec<_>>(), vec!["HK_TEST_TOKEN"]);
+        assert!(read_module(&mut deps, "file:///does/not/exist.pkl").is_err());
+    }
+
+    #[test]
+    fn test_evaluation_error() {
+        let err = evaluation_error(
+            "–– Pkl Error ––\nCannot find property `foo`.\n\n5 | foo = 1\n    ^^^\nat hk (file:///tmp/proj/hk.pkl, line 5)\n",
+        );
+        let Error::PklEval { location, message } = err else {
+            panic!("not a pkl error");
+        };
+        assert_eq!(location.as_deref(), Some("/tmp/proj/hk.pkl:5"));
+        assert!(message.starts_with("Cannot find property `foo`."));
+    }
+}
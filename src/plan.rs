@@ -0,0 +1,197 @@
+use std::{fmt, path::PathBuf};
+
+use itertools::Itertools;
+use serde::Serialize;
+use serde_with::{DisplayFromStr, serde_as};
+
+use crate::{
+    Result,
+    git::StashMethod,
+    step::{CheckType, RunType, SkipReason, Step},
+    step_job::StepJob,
+    tera,
+    ui::style,
+};
+
+/// What `hk run --plan` would do without actually running anything
+///
+/// This is also emitted as JSON with `--plan --json` so the field names are part of the public interface.
+#[serde_as]
+#[derive(Debug, Serialize)]
+pub struct HookPlan {
+    pub name: String,
+    #[serde_as(as = "DisplayFromStr")]
+    pub run_type: RunType,
+    pub stash: StashMethod,
+    pub files: Vec<PathBuf>,
+    pub groups: Vec<GroupPlan>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupPlan {
+    pub name: Option<String>,
+    /// files matched by multiple steps where at least one of them is a fix step
+    pub files_in_contention: Vec<PathBuf>,
+    pub steps: Vec<StepPlan>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StepPlan {
+    pub name: String,
+    pub skip: Option<SkipReason>,
+    pub jobs: Vec<JobPlan>,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+pub struct JobPlan {
+    /// index of this job among the step's jobs (batches or workspaces)
+    pub batch: usize,
+    pub files: Vec<PathBuf>,
+    pub workspace_indicator: Option<PathBuf>,
+    #[serde_as(as = "DisplayFromStr")]
+    pub run_type: RunType,
+    pub command: String,
+    /// the check run type which will be attempted before `run_type` due to files in contention
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub check_first: Option<RunType>,
+    pub check_first_command: Option<String>,
+}
+
+impl StepPlan {
+    pub fn new(step: &Step, jobs: &[StepJob], tctx: &tera::Context) -> Result<Self> {
+        Ok(Self {
+            name: step.name.clone(),
+            skip: None,
+            jobs: jobs
+                .iter()
+                .enumerate()
+                .map(|(batch, job)| JobPlan::new(step, job, batch, tctx))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn skipped(step: &Step, reason: SkipReason) -> Self {
+        Self {
+            name: step.name.clone(),
+            skip: Some(reason),
+            jobs: vec![],
+        }
+    }
+}
+
+impl JobPlan {
+    fn new(step: &Step, job: &StepJob, batch: usize, tctx: &tera::Context) -> Result<Self> {
+        let tctx = job.tctx(tctx);
+        let check_first = job
+            .check_first
+            .then(|| step.available_run_type(RunType::Check(step.check_type())))
+            .flatten();
+        let check_first_command = check_first
+            .map(|run_type| step.render_run_cmd(run_type, &tctx))
+            .transpose()?;
+        Ok(Self {
+            batch,
+            files: job.files.clone(),
+            workspace_indicator: job.workspace_indicator.clone(),
+            run_type: step
+                .available_run_type(job.run_type)
+                .unwrap_or(RunType::Check(CheckType::Check)),
+            command: step.render_run_cmd(job.run_type, &tctx)?,
+            check_first,
+            check_first_command,
+        })
+    }
+}
+
+impl fmt::Display for HookPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} – {} – stash: {} – {} file{}",
+            style::nbold(&self.name),
+            self.run_type,
+            self.stash,
+            self.files.len(),
+            if self.files.len() == 1 { "" } else { "s" }
+        )?;
+        if self.groups.is_empty() {
+            write!(f, "{}", style::ndim("no steps to run"))?;
+        }
+        let mut groups = self.groups.iter().enumerate().map(|(i, group)| {
+            let name = group.name.clone().unwrap_or_else(|| (i + 1).to_string());
+            format!("group {name}\n{group}")
+        });
+        write!(f, "{}", groups.join("\n"))
+    }
+}
+
+impl fmt::Display for GroupPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.files_in_contention.is_empty() {
+            writeln!(
+                f,
+                "  files in contention: {}",
+                self.files_in_contention
+                    .iter()
+                    .map(|f| f.display())
+                    .join(" ")
+            )?;
+        }
+        write!(f, "{}", self.steps.iter().join("\n"))
+    }
+}
+
+impl fmt::Display for StepPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(skip) = &self.skip {
+            return write!(
+                f,
+                "  {} – {}",
+                style::ndim(&self.name),
+                style::ndim(format!("skipped: {skip}"))
+            );
+        }
+        let mut jobs = self.jobs.iter().enumerate().map(|(i, job)| {
+            let name = if self.jobs.len() > 1 {
+                format!("{} [{}/{}]", self.name, i + 1, self.jobs.len())
+            } else {
+                self.name.clone()
+            };
+            format!("  {} – {job}", style::nbold(name))
+        });
+        write!(f, "{}", jobs.join("\n"))
+    }
+}
+
+impl fmt::Display for JobPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(check_first) = self.check_first {
+            write!(
+                f,
+                "{} first, then {}",
+                style::ncyan(check_first),
+                style::ncyan(self.run_type)
+            )?;
+        } else {
+            write!(f, "{}", style::ncyan(self.run_type))?;
+        }
+        if let Some(workspace_indicator) = &self.workspace_indicator {
+            write!(f, " – workspace: {}", workspace_indicator.display())?;
+        }
+        write!(
+            f,
+            " – {} file{}",
+            self.files.len(),
+            if self.files.len() == 1 { "" } else { "s" }
+        )?;
+        if !self.files.is_empty() {
+            write!(
+                f,
+                "\n      {}",
+                self.files.iter().map(|f| f.display()).join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
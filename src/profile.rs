@@ -0,0 +1,101 @@
+use std::sync::LazyLock;
+
+use indexmap::{IndexMap, IndexSet};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{Result, step::EXPR_ENV};
+
+/// A profile declared in the config's `profiles` block
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+pub struct Profile {
+    pub description: Option<String>,
+    /// enabled unless disabled with `!name`
+    #[serde(default)]
+    pub default: bool,
+    /// profiles which are enabled along with this one
+    #[serde(default)]
+    pub implies: Vec<String>,
+    /// an expression which enables the profile if true, e.g.: `env.CI != ""`
+    pub condition: Option<String>,
+}
+
+static ENV_VAR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\benv\.([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+
+impl Profile {
+    /// Evaluates `condition` with `env` holding the environment variables, unset ones are ""
+    pub fn condition_met(&self) -> Result<bool> {
+        let Some(condition) = &self.condition else {
+            return Ok(false);
+        };
+        let mut env = std::env::vars()
+            .map(|(k, v)| (k, expr::Value::String(v)))
+            .collect::<IndexMap<_, _>>();
+        for name in ENV_VAR_RE
+            .captures_iter(condition)
+            .map(|c| c[1].to_string())
+        {
+            env.entry(name)
+                .or_insert_with(|| expr::Value::String(String::new()));
+        }
+        let mut ctx = expr::Context::default();
+        ctx.insert("env", env);
+        let val = EXPR_ENV.eval(condition, &ctx)?;
+        Ok(val == expr::Value::Bool(true))
+    }
+
+    /// Returns a description of every problem found in the profile's config
+    pub fn validate(&self, known: &IndexSet<String>) -> Vec<String> {
+        let mut errors = vec![];
+        if let Some(condition) = &self.condition {
+            if let Err(err) = expr::compile(condition) {
+                errors.push(format!("invalid condition: {err}"));
+            }
+        }
+        for implied in &self.implies {
+            if !known.contains(implied) {
+                errors.push(format!("implies {implied} which is not a profile"));
+            }
+        }
+        errors
+    }
+}
+
+/// Adds the profiles which are enabled by default or by their condition, then every profile
+/// those imply. Disabled profiles are never added.
+pub fn resolve(
+    profiles: &IndexMap<String, Profile>,
+    mut enabled: IndexSet<String>,
+    disabled: &IndexSet<String>,
+) -> IndexSet<String> {
+    for (name, profile) in profiles {
+        if disabled.contains(name) || enabled.contains(name) {
+            continue;
+        }
+        let condition_met = profile.condition_met().unwrap_or_else(|err| {
+            warn!("profile {name}: condition failed: {err}");
+            false
+        });
+        if profile.default || condition_met {
+            enabled.insert(name.clone());
+        }
+    }
+    let mut i = 0;
+    while let Some(name) = enabled.get_index(i).cloned() {
+        for implied in profiles
+            .get(&name)
+            .map(|p| &p.implies)
+            .into_iter()
+            .flatten()
+        {
+            if !disabled.contains(implied) {
+                enabled.insert(implied.clone());
+            }
+        }
+        i += 1;
+    }
+    enabled
+}
@@ -0,0 +1,113 @@
+use crate::{
+    diagnostics::Level,
+    results::{JobResult, Outcome},
+};
+
+/// How a hook's progress and results are shown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Reporter {
+    /// progress bars in a terminal, plain text otherwise
+    #[default]
+    Default,
+    /// GitHub Actions: each step's output in a collapsible group and diagnostics as annotations
+    Github,
+}
+
+impl Reporter {
+    /// Whether job output should be shown as it is produced
+    pub fn live_output(&self) -> bool {
+        *self == Reporter::Default
+    }
+
+    /// Called once every job of a step has finished with the results of those jobs
+    pub fn step_finished(&self, step: &str, results: &[JobResult]) {
+        match self {
+            Reporter::Default => {}
+            Reporter::Github => github_step(step, results),
+        }
+    }
+}
+
+/// e.g.: "passed" or "failed: exit code 1"
+fn status(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Passed => "passed".to_string(),
+        Outcome::Warned(message) => format!("warning: {message}"),
+        Outcome::Failed(message) => format!("failed: {message}"),
+        Outcome::Skipped(reason) => format!("skipped: {reason}"),
+    }
+}
+
+/// One group for the step titled with its worst outcome, holding the output of each of its jobs
+fn github_step(step: &str, results: &[JobResult]) {
+    let rank = |outcome: &Outcome| match outcome {
+        Outcome::Failed(_) => 0,
+        Outcome::Warned(_) => 1,
+        Outcome::Passed => 2,
+        Outcome::Skipped(_) => 3,
+    };
+    let Some(worst) = results.iter().map(|r| &r.outcome).min_by_key(|o| rank(o)) else {
+        return;
+    };
+    let mut out = format!("::group::{step} – {}\n", status(worst));
+    for result in results {
+        if results.len() > 1 {
+            out.push_str(&format!(
+                "{} – {}\n",
+                result.name(),
+                status(&result.outcome)
+            ));
+        }
+        if let Some(command) = &result.command {
+            out.push_str(&format!("$ {command}\n"));
+        }
+        for output in [&result.stdout, &result.stderr] {
+            let output = console::strip_ansi_codes(output);
+            if !output.trim().is_empty() {
+                out.push_str(output.trim_end());
+                out.push('\n');
+            }
+        }
+    }
+    out.push_str("::endgroup::\n");
+    for diagnostic in results.iter().flat_map(|r| r.diagnostics.iter().flatten()) {
+        let command = match diagnostic.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "notice",
+        };
+        let mut props = vec![format!(
+            "file={}",
+            escape_property(&diagnostic.file.to_string_lossy())
+        )];
+        if let Some(line) = diagnostic.line {
+            props.push(format!("line={line}"));
+        }
+        if let Some(column) = diagnostic.column {
+            props.push(format!("col={column}"));
+        }
+        let title = match &diagnostic.rule {
+            Some(rule) => format!("{step} ({rule})"),
+            None => step.to_string(),
+        };
+        props.push(format!("title={}", escape_property(&title)));
+        out.push_str(&format!(
+            "::{command} {}::{}\n",
+            props.join(","),
+            escape_data(&diagnostic.message)
+        ));
+    }
+    print!("{out}");
+}
+
+/// https://github.com/actions/toolkit/blob/main/packages/core/src/command.ts
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
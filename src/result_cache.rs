@@ -0,0 +1,68 @@
+//! Remembers which files a step's check command passed on so they aren't checked again.
+//!
+//! An entry is an empty file under `HK_CACHE_DIR/results/<step>/` named after the hash of a
+//! file's path and contents. The step directory is named after the hash of hk's version, the
+//! step's whole config and its rendered command and env so changing the step (its command, env,
+//! globs…) or a variable the command uses (e.g.: `{{workspace}}`) invalidates it.
+//!
+//! An entry's mtime is updated whenever it is used so `hk cache prune` only removes entries which
+//! haven't been used recently.
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{Result, env, hash::hash_to_str, step::Step};
+
+pub struct ResultCache {
+    dir: PathBuf,
+    /// where the step's file paths are relative to
+    root: Option<PathBuf>,
+}
+
+impl ResultCache {
+    pub fn dir() -> PathBuf {
+        env::HK_CACHE_DIR.join("results")
+    }
+
+    pub fn new(step: &Step, run: &str, env: &[(&str, String)]) -> Result<Self> {
+        let config = serde_json::to_string(step)?;
+        let key = hash_to_str(&(env!("CARGO_PKG_VERSION"), config, run, env));
+        Ok(Self {
+            dir: Self::dir().join(format!("{}-{key}", step.name)),
+            root: step.dir.as_ref().map(PathBuf::from),
+        })
+    }
+
+    pub fn contains(&self, file: &Path) -> bool {
+        let Some(entry) = self.entry(file).filter(|entry| entry.exists()) else {
+            return false;
+        };
+        let touched = std::fs::File::options()
+            .write(true)
+            .open(&entry)
+            .and_then(|f| f.set_modified(SystemTime::now()));
+        if let Err(err) = touched {
+            debug!("failed to touch {}: {err}", entry.display());
+        }
+        true
+    }
+
+    pub fn add(&self, files: &[PathBuf]) -> Result<()> {
+        for entry in files.iter().filter_map(|f| self.entry(f)) {
+            xx::file::write(entry, "")?;
+        }
+        Ok(())
+    }
+
+    /// Files that can't be read (e.g.: deleted files) are never cached
+    fn entry(&self, file: &Path) -> Option<PathBuf> {
+        let path = match &self.root {
+            Some(root) => root.join(file),
+            None => file.to_path_buf(),
+        };
+        let contents = std::fs::read(path).ok()?;
+        Some(self.dir.join(hash_to_str(&(file, contents))))
+    }
+}
@@ -0,0 +1,95 @@
+use std::{sync::Mutex, time::Duration};
+
+use crate::{diagnostics::Diagnostic, error, summary};
+
+/// The outcome of every job (and skipped step) in a hook, used for reports like `--junit`
+#[derive(Debug, Default)]
+pub struct Results {
+    results: Mutex<Vec<JobResult>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JobResult {
+    pub step: String,
+    /// e.g.: "2/3" if the step was split into multiple jobs
+    pub job: Option<String>,
+    pub outcome: Outcome,
+    pub duration: Duration,
+    pub command: Option<String>,
+    pub stdout: String,
+    pub stderr: String,
+    /// parsed from the output if the step has an `output_parser`
+    pub diagnostics: Option<Vec<Diagnostic>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// failed but the step was allowed to fail
+    Warned(String),
+    Failed(String),
+    Skipped(String),
+}
+
+impl Results {
+    pub fn add(&self, result: JobResult) {
+        self.results.lock().unwrap().push(result);
+    }
+
+    pub fn all(&self) -> Vec<JobResult> {
+        self.results.lock().unwrap().clone()
+    }
+
+    pub fn for_step(&self, step: &str) -> Vec<JobResult> {
+        let results = self.results.lock().unwrap();
+        results.iter().filter(|r| r.step == step).cloned().collect()
+    }
+}
+
+impl JobResult {
+    pub fn new(step: &str, job: Option<String>, outcome: Outcome, duration: Duration) -> Self {
+        Self {
+            step: step.to_string(),
+            job,
+            outcome,
+            duration,
+            command: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            diagnostics: None,
+        }
+    }
+
+    pub fn skipped(step: &str, reason: impl ToString) -> Self {
+        Self::new(
+            step,
+            None,
+            Outcome::Skipped(reason.to_string()),
+            Duration::ZERO,
+        )
+    }
+
+    pub fn with_output(mut self, output: Option<&ensembler::CmdResult>) -> Self {
+        if let Some(output) = output {
+            self.stdout = output.stdout.clone();
+            self.stderr = output.stderr.clone();
+        }
+        self
+    }
+
+    /// Uses the output and message of a failed command
+    pub fn with_error(mut self, err: &eyre::Report) -> Self {
+        if let Some(e) = error::script_failed(err) {
+            self.command = Some(summary::command(&e.0, &e.1));
+            self = self.with_output(Some(&e.3));
+        }
+        self
+    }
+
+    pub fn name(&self) -> String {
+        match &self.job {
+            Some(job) => format!("{} [{job}]", self.step),
+            None => self.step.clone(),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use indexmap::IndexMap;
+use serde_json::{Value, json};
+
+use crate::{Result, diagnostics::Diagnostic, results::JobResult};
+
+/// Writes a SARIF 2.1.0 log with a run for each step that has an `output_parser`
+pub fn write(path: &Path, results: &[JobResult]) -> Result<()> {
+    let mut by_step: IndexMap<&str, Vec<&Diagnostic>> = IndexMap::new();
+    for result in results {
+        if let Some(diagnostics) = &result.diagnostics {
+            by_step
+                .entry(&result.step)
+                .or_default()
+                .extend(diagnostics.iter());
+        }
+    }
+    let runs = by_step
+        .into_iter()
+        .map(|(step, diagnostics)| {
+            json!({
+                "tool": { "driver": { "name": step } },
+                "results": diagnostics.into_iter().map(sarif_result).collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let log = json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": runs,
+    });
+    xx::file::write(path, serde_json::to_string_pretty(&log)?)?;
+    Ok(())
+}
+
+fn sarif_result(diagnostic: &Diagnostic) -> Value {
+    let mut region = serde_json::Map::new();
+    if let Some(line) = diagnostic.line.filter(|l| *l > 0) {
+        region.insert("startLine".into(), line.into());
+        if let Some(column) = diagnostic.column.filter(|c| *c > 0) {
+            region.insert("startColumn".into(), column.into());
+        }
+    }
+    let mut physical_location = json!({
+        "artifactLocation": {
+            "uri": diagnostic.file.to_string_lossy().replace('\\', "/"),
+        },
+    });
+    if !region.is_empty() {
+        physical_location["region"] = region.into();
+    }
+    let mut result = json!({
+        "level": diagnostic.level.to_string(),
+        "message": { "text": diagnostic.message },
+        "locations": [{ "physicalLocation": physical_location }],
+    });
+    if let Some(rule) = &diagnostic.rule {
+        result["ruleId"] = rule.clone().into();
+    }
+    result
+}
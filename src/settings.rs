@@ -3,23 +3,74 @@ use std::{
     sync::{LazyLock, Mutex},
 };
 
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
 
-use crate::env;
+use crate::{
+    env,
+    git::StashMethod,
+    profile::{self, Profile},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Settings {
     pub jobs: NonZero<usize>,
     pub enabled_profiles: IndexSet<String>,
     pub disabled_profiles: IndexSet<String>,
     pub fail_fast: bool,
+    /// used by hooks which don't set their own `stash`
+    pub stash: Option<StashMethod>,
+    pub check_first: bool,
+    pub hide_when_done: bool,
+}
+
+/// The `settings` block of a config file
+///
+/// These are only defaults: CLI flags and env vars take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+pub struct ConfigSettings {
+    pub jobs: Option<NonZero<usize>>,
+    pub fail_fast: Option<bool>,
+    /// prefix with ! to disable
+    pub profiles: Option<Vec<String>>,
+    pub stash: Option<StashMethod>,
+    pub check_first: Option<bool>,
+    pub hide_when_done: Option<bool>,
+}
+
+impl ConfigSettings {
+    /// Layers `other` over these settings, returning the names of the settings it set
+    pub fn merge(&mut self, other: ConfigSettings) -> Vec<&'static str> {
+        let mut set = vec![];
+        macro_rules! merge {
+            ($($field:ident),*) => {
+                $(
+                    if other.$field.is_some() {
+                        self.$field = other.$field;
+                        set.push(stringify!($field));
+                    }
+                )*
+            };
+        }
+        merge!(
+            jobs,
+            fail_fast,
+            profiles,
+            stash,
+            check_first,
+            hide_when_done
+        );
+        set
+    }
 }
 
 static JOBS: LazyLock<Mutex<Option<NonZero<usize>>>> = LazyLock::new(Default::default);
-static ENABLED_PROFILES: LazyLock<Mutex<Option<IndexSet<String>>>> =
-    LazyLock::new(Default::default);
-static DISABLED_PROFILES: LazyLock<Mutex<Option<IndexSet<String>>>> =
-    LazyLock::new(Default::default);
+/// `--profile` entries in the order they were passed, e.g.: `["slow", "!fast"]`
+static CLI_PROFILES: LazyLock<Mutex<Vec<String>>> = LazyLock::new(Default::default);
+static CONFIG: LazyLock<Mutex<ConfigSettings>> = LazyLock::new(Default::default);
+static PROFILES: LazyLock<Mutex<IndexMap<String, Profile>>> = LazyLock::new(Default::default);
 
 impl Settings {
     pub fn get() -> &'static Self {
@@ -28,58 +79,91 @@ impl Settings {
     }
 
     pub fn with_profiles(profiles: &[String]) {
-        for profile in profiles {
-            if profile.starts_with('!') {
-                let profile = profile.strip_prefix('!').unwrap();
-                let mut disabled_profiles = DISABLED_PROFILES.lock().unwrap();
-                disabled_profiles
-                    .get_or_insert_default()
-                    .insert(profile.to_string());
-            } else {
-                let mut enabled_profiles = ENABLED_PROFILES.lock().unwrap();
-                enabled_profiles
-                    .get_or_insert_default()
-                    .insert(profile.to_string());
-                let mut disabled_profiles = DISABLED_PROFILES.lock().unwrap();
-                disabled_profiles
-                    .get_or_insert_default()
-                    .shift_remove(profile);
-            }
-        }
+        CLI_PROFILES
+            .lock()
+            .unwrap()
+            .extend(profiles.iter().cloned());
     }
 
     pub fn set_jobs(jobs: NonZero<usize>) {
         *JOBS.lock().unwrap() = Some(jobs);
     }
+
+    /// Must be called before the first `Settings::get()`
+    pub fn set_config(settings: &ConfigSettings, profiles: &IndexMap<String, Profile>) {
+        *CONFIG.lock().unwrap() = settings.clone();
+        *PROFILES.lock().unwrap() = profiles.clone();
+    }
+
+    /// Profiles passed with `--profile`
+    pub fn cli_profiles() -> IndexSet<String> {
+        CLI_PROFILES
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|p| p.strip_prefix('!').unwrap_or(p).to_string())
+            .collect()
+    }
+}
+
+fn env_set(names: &[&str]) -> bool {
+    names.iter().any(|name| env::var(name).is_ok())
 }
 
 impl Default for Settings {
     fn default() -> Self {
-        let disabled_profiles: IndexSet<String> = DISABLED_PROFILES
-            .lock()
-            .unwrap()
+        let config = CONFIG.lock().unwrap().clone();
+        // config, then env, then CLI so each one only overrides the profiles it names
+        let mut enabled_profiles = IndexSet::new();
+        let mut disabled_profiles = IndexSet::new();
+        let profiles = config
+            .profiles
             .clone()
-            .unwrap_or_else(|| {
-                env::HK_PROFILE
-                    .iter()
-                    .filter(|p| p.starts_with('!'))
-                    .map(|p| p.strip_prefix('!').unwrap().to_string())
-                    .collect()
-            });
-        let enabled_profiles: IndexSet<String> =
-            ENABLED_PROFILES.lock().unwrap().clone().unwrap_or_else(|| {
-                env::HK_PROFILE
-                    .iter()
-                    .filter(|p| !disabled_profiles.contains(*p))
-                    .filter(|p| !p.starts_with('!'))
-                    .map(|p| p.to_string())
-                    .collect()
-            });
+            .unwrap_or_default()
+            .into_iter()
+            .chain(env::HK_PROFILE.iter().cloned())
+            .chain(CLI_PROFILES.lock().unwrap().clone());
+        for profile in profiles {
+            match profile.strip_prefix('!') {
+                Some(profile) => {
+                    enabled_profiles.shift_remove(profile);
+                    disabled_profiles.insert(profile.to_string());
+                }
+                None => {
+                    disabled_profiles.shift_remove(&profile);
+                    enabled_profiles.insert(profile);
+                }
+            }
+        }
+        let enabled_profiles = profile::resolve(
+            &PROFILES.lock().unwrap(),
+            enabled_profiles,
+            &disabled_profiles,
+        );
+        let jobs = if env_set(&["HK_JOBS", "HK_JOB"]) {
+            *env::HK_JOBS
+        } else {
+            config.jobs.unwrap_or(*env::HK_JOBS)
+        };
+        let env_or_config = |name: &str, env: bool, config: Option<bool>| {
+            if env_set(&[name]) {
+                env
+            } else {
+                config.unwrap_or(env)
+            }
+        };
         Self {
-            jobs: JOBS.lock().unwrap().unwrap_or(*env::HK_JOBS),
+            jobs: JOBS.lock().unwrap().unwrap_or(jobs),
             enabled_profiles,
             disabled_profiles,
-            fail_fast: *env::HK_FAIL_FAST,
+            fail_fast: env_or_config("HK_FAIL_FAST", *env::HK_FAIL_FAST, config.fail_fast),
+            stash: env::HK_STASH.or(config.stash),
+            check_first: env_or_config("HK_CHECK_FIRST", *env::HK_CHECK_FIRST, config.check_first),
+            hide_when_done: env_or_config(
+                "HK_HIDE_WHEN_DONE",
+                *env::HK_HIDE_WHEN_DONE,
+                config.hide_when_done,
+            ),
         }
     }
 }
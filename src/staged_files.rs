@@ -0,0 +1,105 @@
+//! Partially staged files when hk isn't stashing unstaged changes.
+//!
+//! The index version of each file is written to a temporary directory so check steps can lint
+//! what is about to be committed (see `Hook::check_staged`) and a copy of the working tree
+//! version is kept so only the changes fix steps make get staged (see `stage_fixes`).
+//!
+//! Paths keep their layout under the directory (e.g.: `<dir>/index/src/main.rs`) so linters
+//! which look at file names or extensions behave the same.
+
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+
+use crate::{Result, git::Git};
+
+pub struct StagedFiles {
+    dir: PathBuf,
+    /// file relative to the repo root -> where its staged contents were written
+    files: IndexMap<PathBuf, PathBuf>,
+}
+
+impl StagedFiles {
+    pub fn new<'a>(git: &Git, files: impl IntoIterator<Item = &'a PathBuf>) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("hk-staged-{}", std::process::id()));
+        let mut staged = Self {
+            dir,
+            files: IndexMap::new(),
+        };
+        for file in files {
+            let path = staged.dir.join("index").join(file);
+            xx::file::write(&path, git.staged_contents(file)?)?;
+            xx::file::write(staged.worktree(file), std::fs::read(file)?)?;
+            staged.files.insert(file.clone(), path);
+        }
+        Ok(staged)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn contains(&self, file: &Path, dir: Option<&str>) -> bool {
+        self.get(file, dir).is_some()
+    }
+
+    /// Where the staged contents of `file` were written, `file` is relative to the step's `dir`
+    pub fn get(&self, file: &Path, dir: Option<&str>) -> Option<&Path> {
+        let file = match dir {
+            Some(dir) => Path::new(dir).join(file),
+            None => file.to_path_buf(),
+        };
+        self.files.get(&file).map(|p| p.as_path())
+    }
+
+    /// Replaces files with their staged contents where there are any
+    pub fn replace(&self, files: &[PathBuf], dir: Option<&str>) -> Vec<PathBuf> {
+        files
+            .iter()
+            .map(|f| self.get(f, dir).map(PathBuf::from).unwrap_or(f.clone()))
+            .collect()
+    }
+
+    /// Rewrites paths of staged contents in a command's output back to the files they came from
+    pub fn restore(&self, output: &str, dir: Option<&str>) -> String {
+        let mut output = output.to_string();
+        for (file, path) in &self.files {
+            let file = match dir {
+                Some(dir) => file.strip_prefix(dir).unwrap_or(file),
+                None => file,
+            };
+            output = output.replace(&*path.to_string_lossy(), &file.to_string_lossy());
+        }
+        output
+    }
+
+    /// Stages the changes made to `file` in the working tree since it was last seen on top of
+    /// its staged contents, leaving its unstaged changes unstaged.
+    /// Returns false without staging anything if the changes overlap the unstaged ones.
+    pub fn stage_fixes(&self, git: &Git, file: &Path) -> Result<bool> {
+        let Some(index) = self.files.get(file) else {
+            return Ok(false);
+        };
+        let worktree = self.worktree(file);
+        let Some(merged) = git.merge_file(index, &worktree, file)? else {
+            return Ok(false);
+        };
+        git.stage_contents(file, &merged)?;
+        xx::file::write(index, &merged)?;
+        xx::file::write(&worktree, std::fs::read(file)?)?;
+        Ok(true)
+    }
+
+    /// The working tree version of `file` when it was last staged
+    fn worktree(&self, file: &Path) -> PathBuf {
+        self.dir.join("worktree").join(file)
+    }
+}
+
+impl Drop for StagedFiles {
+    fn drop(&mut self) {
+        if let Err(err) = xx::file::remove_dir_all(&self.dir) {
+            warn!("failed to remove {}: {err:?}", self.dir.display());
+        }
+    }
+}
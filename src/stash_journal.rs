@@ -0,0 +1,160 @@
+//! Records unstaged changes hk has stashed so they can be recovered if hk is killed before it
+//! restores them (e.g.: a second ctrl-c).
+//!
+//! An entry is written to `HK_STATE_DIR/stashes/<id>.json` before the working tree is modified
+//! and removed once the changes have been restored. Entries left behind are shown by
+//! `hk stash list` and re-applied with `hk stash restore`.
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use eyre::{bail, eyre};
+use serde::{Deserialize, Serialize};
+
+use crate::{Result, env, git::StashMethod};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StashJournal {
+    pub id: String,
+    /// the repository the changes were stashed in
+    pub root: PathBuf,
+    pub method: StashMethod,
+    pub created: SystemTime,
+    /// the hk process which stashed the changes
+    pub pid: u32,
+    /// the patch holding the changes for `patch-file`
+    pub patch_file: Option<PathBuf>,
+    /// the stash commit for `git`, unknown until `git stash` has finished
+    pub commit: Option<String>,
+    /// files added with `git add --intent-to-add` which were removed from the index
+    #[serde(default)]
+    pub intent_to_add: Vec<PathBuf>,
+}
+
+impl StashJournal {
+    pub fn dir() -> PathBuf {
+        env::HK_STATE_DIR.join("stashes")
+    }
+
+    pub fn new(root: &Path, method: StashMethod, patch_file: Option<PathBuf>) -> Self {
+        let date = chrono::Local::now().format("%Y-%m-%d");
+        let rand = getrandom::u32().unwrap_or_default();
+        Self {
+            id: format!("{date}-{rand:08x}"),
+            root: root.to_path_buf(),
+            method,
+            created: SystemTime::now(),
+            pid: std::process::id(),
+            patch_file,
+            commit: None,
+            intent_to_add: vec![],
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        Self::dir().join(format!("{}.json", self.id))
+    }
+
+    pub fn write(&self) -> Result<()> {
+        xx::file::write(self.path(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        xx::file::remove_file(self.path())?;
+        Ok(())
+    }
+
+    /// Entries for the repository at `root`, oldest first
+    pub fn list(root: &Path) -> Result<Vec<Self>> {
+        let dir = Self::dir();
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut entries = vec![];
+        for file in xx::file::ls(&dir)? {
+            let entry = xx::file::read_to_string(&file)
+                .ok()
+                .and_then(|raw| serde_json::from_str::<Self>(&raw).ok());
+            match entry {
+                Some(entry) if entry.root == root => entries.push(entry),
+                Some(_) => {}
+                None => warn!("failed to read stash journal {}", file.display()),
+            }
+        }
+        entries.sort_by_key(|e| e.created);
+        Ok(entries)
+    }
+
+    /// Entries whose hk process is no longer running so the changes will never be restored
+    pub fn leftover(root: &Path) -> Result<Vec<Self>> {
+        Ok(Self::list(root)?
+            .into_iter()
+            .filter(|e| !e.is_running())
+            .collect())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.pid == std::process::id() || process_running(self.pid)
+    }
+
+    /// Re-applies the stashed changes to the working tree and removes the entry
+    pub fn restore(&self) -> Result<()> {
+        match self.method {
+            StashMethod::PatchFile => {
+                let patch_file = self
+                    .patch_file
+                    .as_ref()
+                    .ok_or_else(|| eyre!("stash {} has no patch file", self.id))?;
+                if !patch_file.exists() {
+                    bail!("patch file {} no longer exists", patch_file.display());
+                }
+                xx::process::cmd("git", ["apply", "--reject"])
+                    .arg(patch_file)
+                    .run()?;
+                if let Err(err) = xx::file::remove_file(patch_file) {
+                    debug!("failed to remove patch file: {err:?}");
+                }
+            }
+            StashMethod::Git => {
+                let Some(commit) = &self.commit else {
+                    bail!(
+                        "stash {} was interrupted before `git stash` finished, look for an entry named hk in `git stash list`",
+                        self.id
+                    );
+                };
+                let stashes = xx::process::cmd("git", ["stash", "list", "--format=%H"]).read()?;
+                match stashes.lines().position(|s| s == commit) {
+                    Some(n) => xx::process::cmd("git", ["stash", "pop"])
+                        .arg(format!("stash@{{{n}}}"))
+                        .run()?,
+                    None => xx::process::cmd("git", ["stash", "apply", commit]).run()?,
+                };
+            }
+            StashMethod::None => {}
+        }
+        if !self.intent_to_add.is_empty() {
+            xx::process::cmd("git", ["add", "--intent-to-add", "--"])
+                .args(&self.intent_to_add)
+                .run()?;
+        }
+        self.remove()
+    }
+}
+
+#[cfg(unix)]
+fn process_running(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+#[cfg(not(unix))]
+fn process_running(_pid: u32) -> bool {
+    false
+}
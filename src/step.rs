@@ -1,22 +1,44 @@
-use crate::{Result, error::Error, step_job::StepJob};
+use crate::{
+    Result,
+    diagnostics::OutputParser,
+    error,
+    error::Error,
+    file_types::FileType,
+    job_processes::JobCmd,
+    result_cache::ResultCache,
+    results::{JobResult, Outcome},
+    step_job::StepJob,
+    summary::{self, Severity},
+    timings::TimingKind,
+};
 use crate::{env, step_job::StepJobStatus};
 use crate::{glob, settings::Settings};
 use crate::{step_context::StepContext, tera};
 use clx::progress::{ProgressJob, ProgressJobBuilder, ProgressJobDoneBehavior, ProgressStatus};
-use ensembler::CmdLineRunner;
 use eyre::{WrapErr, eyre};
 use indexmap::{IndexMap, IndexSet};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use serde_with::{DisplayFromStr, OneOrMany, PickFirst, serde_as};
+use serde_with::{
+    DisplayFromStr, DurationSecondsWithFrac, OneOrMany, PickFirst, formats::Flexible, serde_as,
+};
 use shell_quote::QuoteInto;
 use shell_quote::QuoteRefExt;
-use std::{collections::HashSet, fmt::Display, path::PathBuf, str::FromStr};
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use std::{
     ffi::OsString,
     sync::{Arc, LazyLock},
 };
-use std::{fmt, process::Stdio};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
 use tokio::sync::OwnedSemaphorePermit;
 use xx::file::display_path;
 
@@ -27,10 +49,15 @@ use xx::file::display_path;
 pub struct Step {
     #[serde(default)]
     pub name: String,
+    /// `false` removes the step, e.g.: to turn off a project's step in `hk.local.pkl`
+    pub enabled: Option<bool>,
     pub profiles: Option<Vec<String>>,
     #[serde_as(as = "Option<OneOrMany<_>>")]
     #[serde(default)]
     pub glob: Option<Vec<String>>,
+    #[serde_as(as = "Option<OneOrMany<_>>")]
+    #[serde(default)]
+    pub file_types: Option<Vec<FileType>>,
     #[serde(default)]
     pub interactive: bool,
     pub depends: Vec<String>,
@@ -57,11 +84,31 @@ pub struct Step {
     pub env: IndexMap<String, String>,
     pub stage: Option<Vec<String>>,
     pub exclude: Option<Vec<String>>,
+    #[serde_as(as = "Option<OneOrMany<_>>")]
+    #[serde(default)]
+    pub types: Option<Vec<FileKind>>,
     #[serde(default)]
     pub exclusive: bool,
     pub root: Option<PathBuf>,
     #[serde(default)]
     pub hide: bool,
+    /// skip check jobs on files which already passed, see `ResultCache`
+    #[serde(default)]
+    pub cache: bool,
+    #[serde_as(as = "Option<DurationSecondsWithFrac<f64, Flexible>>")]
+    #[serde(default)]
+    pub timeout: Option<Duration>,
+    #[serde(default)]
+    pub on_timeout: OnTimeout,
+    #[serde(default)]
+    pub allow_failure: bool,
+    #[serde(default)]
+    pub retries: usize,
+    #[serde_as(as = "DurationSecondsWithFrac<f64, Flexible>")]
+    #[serde(default)]
+    pub retry_delay: Duration,
+    pub output_parser: Option<OutputParser>,
+    pub output_pattern: Option<String>,
 }
 
 impl fmt::Display for Step {
@@ -70,7 +117,7 @@ impl fmt::Display for Step {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum FileKind {
     Text,
@@ -81,6 +128,60 @@ pub enum FileKind {
     NotSymlink,
 }
 
+impl FileKind {
+    /// Whether `path` is of every one of `kinds`, files that don't exist match nothing
+    fn matches_all(kinds: &[FileKind], path: &Path) -> bool {
+        let Ok(metadata) = path.symlink_metadata() else {
+            return false;
+        };
+        let mut is_binary = None;
+        kinds.iter().all(|kind| match kind {
+            FileKind::Text | FileKind::Binary => {
+                let binary = *is_binary.get_or_insert_with(|| is_binary_file(path));
+                binary == (*kind == FileKind::Binary)
+            }
+            FileKind::Executable => is_executable(&metadata),
+            FileKind::NotExecutable => !is_executable(&metadata),
+            FileKind::Symlink => metadata.is_symlink(),
+            FileKind::NotSymlink => !metadata.is_symlink(),
+        })
+    }
+}
+
+/// Uses the same heuristic as git: a file is binary if there is a NUL byte in its first 8000 bytes
+fn is_binary_file(path: &Path) -> bool {
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    let mut buf = Vec::with_capacity(8000);
+    if file.take(8000).read_to_end(&mut buf).is_err() {
+        return false;
+    }
+    buf.contains(&0)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// What happens when a step runs longer than its `timeout`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnTimeout {
+    /// the step fails
+    #[default]
+    Error,
+    /// the step is killed but only a warning is shown
+    Warn,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RunType {
     Check(CheckType),
@@ -94,6 +195,17 @@ pub enum CheckType {
     Diff,
 }
 
+impl Display for RunType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunType::Check(CheckType::Check) => write!(f, "check"),
+            RunType::Check(CheckType::ListFiles) => write!(f, "check_list_files"),
+            RunType::Check(CheckType::Diff) => write!(f, "check_diff"),
+            RunType::Fix => write!(f, "fix"),
+        }
+    }
+}
+
 impl Step {
     pub(crate) fn init(&mut self, name: &str) {
         self.name = name.to_string();
@@ -103,19 +215,29 @@ impl Step {
     }
 
     pub fn run_cmd(&self, run_type: RunType) -> Option<&Script> {
+        self.available_run_type(run_type)
+            .and_then(|run_type| self.script(run_type))
+    }
+
+    /// The run type whose command will actually be used when `run_type` is requested,
+    /// e.g.: a fix request falls back to check if the step has no fix command
+    pub fn available_run_type(&self, run_type: RunType) -> Option<RunType> {
         match run_type {
-            RunType::Check(c) => match c {
-                CheckType::Check => self.check.as_ref(),
-                CheckType::Diff => self.check_diff.as_ref(),
-                CheckType::ListFiles => self.check_list_files.as_ref(),
-            }
-            .or(self.check.as_ref())
-            .or(self.check_list_files.as_ref())
-            .or(self.check_diff.as_ref()),
-            RunType::Fix => self
-                .fix
-                .as_ref()
-                .or_else(|| self.run_cmd(RunType::Check(CheckType::Check))),
+            RunType::Check(c) => [c, CheckType::Check, CheckType::ListFiles, CheckType::Diff]
+                .into_iter()
+                .map(RunType::Check)
+                .find(|run_type| self.script(*run_type).is_some()),
+            RunType::Fix if self.fix.is_some() => Some(RunType::Fix),
+            RunType::Fix => self.available_run_type(RunType::Check(CheckType::Check)),
+        }
+    }
+
+    fn script(&self, run_type: RunType) -> Option<&Script> {
+        match run_type {
+            RunType::Check(CheckType::Check) => self.check.as_ref(),
+            RunType::Check(CheckType::Diff) => self.check_diff.as_ref(),
+            RunType::Check(CheckType::ListFiles) => self.check_list_files.as_ref(),
+            RunType::Fix => self.fix.as_ref(),
         }
     }
 
@@ -149,12 +271,83 @@ impl Step {
         })
     }
 
-    pub fn is_profile_enabled(&self) -> bool {
-        is_profile_enabled(
-            &self.name,
-            self.enabled_profiles(),
-            self.disabled_profiles(),
-        )
+    /// Returns why this step would not run at all for the given run type, if it would be skipped
+    pub fn skip_reason(&self, run_type: RunType) -> Option<SkipReason> {
+        if self.run_cmd(run_type).is_none() {
+            Some(SkipReason::NoRunCommand)
+        } else if env::HK_SKIP_STEPS.contains(&self.name) {
+            Some(SkipReason::SkipSteps)
+        } else {
+            profile_skip_reason(self.enabled_profiles(), self.disabled_profiles())
+        }
+    }
+
+    /// Evaluates `condition`, returning false if the step should not run
+    pub fn condition_met(&self, ctx: &expr::Context) -> Result<bool> {
+        let Some(condition) = &self.condition else {
+            return Ok(true);
+        };
+        let val = EXPR_ENV.eval(condition, ctx)?;
+        trace!("{self}: condition: {condition} = {val}");
+        Ok(val != expr::Value::Bool(false))
+    }
+
+    /// Returns a description of every problem found in the step's config
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        if self.check.is_none()
+            && self.check_list_files.is_none()
+            && self.check_diff.is_none()
+            && self.fix.is_none()
+        {
+            errors.push("no check or fix command".to_string());
+        }
+        for (key, globs) in [("glob", &self.glob), ("exclude", &self.exclude)] {
+            if let Err(err) = glob::build(globs.as_deref().unwrap_or_default()) {
+                errors.push(format!("invalid {key}: {err}"));
+            }
+        }
+        let scripts = [
+            ("check", &self.check),
+            ("check_list_files", &self.check_list_files),
+            ("check_diff", &self.check_diff),
+            ("fix", &self.fix),
+        ];
+        for (key, script) in scripts {
+            for template in script.iter().flat_map(|s| s.all()) {
+                if let Err(err) = tera::parse(template) {
+                    errors.push(format!("invalid template in {key}: {err:#}"));
+                }
+            }
+        }
+        for (key, value) in &self.env {
+            if let Err(err) = tera::parse(value) {
+                errors.push(format!("invalid template in env.{key}: {err:#}"));
+            }
+        }
+        for stage in self.stage.iter().flatten() {
+            if let Err(err) = tera::parse(stage) {
+                errors.push(format!("invalid template in stage: {err:#}"));
+            }
+        }
+        if let Some(condition) = &self.condition {
+            if let Err(err) = expr::compile(condition) {
+                errors.push(format!("invalid condition: {err}"));
+            }
+        }
+        if let Some(pattern) = &self.output_pattern {
+            match regex::Regex::new(pattern) {
+                Ok(re) => {
+                    for group in ["file", "message"] {
+                        if !re.capture_names().flatten().any(|name| name == group) {
+                            errors.push(format!("output_pattern has no {group} group"));
+                        }
+                    }
+                }
+                Err(err) => errors.push(format!("invalid output_pattern: {err}")),
+            }
+        }
+        errors
     }
 
     pub(crate) fn build_step_progress(&self) -> Arc<ProgressJob> {
@@ -166,7 +359,7 @@ impl Step {
             .prop("name", &self.name)
             .prop("files", &0)
             .status(ProgressStatus::Hide)
-            .on_done(if *env::HK_HIDE_WHEN_DONE {
+            .on_done(if Settings::get().hide_when_done {
                 ProgressJobDoneBehavior::Hide
             } else {
                 ProgressJobDoneBehavior::Keep
@@ -198,7 +391,7 @@ impl Step {
         Ok(Some(workspaces))
     }
 
-    fn filter_files(&self, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    pub(crate) fn filter_files(&self, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
         let mut files = files.to_vec();
         if let Some(dir) = &self.dir {
             files.retain(|f| f.starts_with(dir));
@@ -210,8 +403,22 @@ impl Step {
                 *f = f.strip_prefix(dir).unwrap_or(f).to_path_buf();
             }
         }
-        if let Some(glob) = &self.glob {
-            files = glob::get_matches(glob, &files)?;
+        match (&self.glob, &self.file_types) {
+            (Some(glob), None) => files = glob::get_matches(glob, &files)?,
+            (glob, Some(file_types)) => {
+                let globbed: HashSet<PathBuf> = match glob {
+                    Some(glob) => glob::get_matches(glob, &files)?.into_iter().collect(),
+                    None => Default::default(),
+                };
+                files.retain(|f| {
+                    globbed.contains(f)
+                        || match &self.dir {
+                            Some(dir) => FileType::matches_any(file_types, &Path::new(dir).join(f)),
+                            None => FileType::matches_any(file_types, f),
+                        }
+                });
+            }
+            (None, None) => {}
         }
         if let Some(exclude) = &self.exclude {
             let excluded = glob::get_matches(exclude, &files)?
@@ -219,6 +426,12 @@ impl Step {
                 .collect::<HashSet<_>>();
             files.retain(|f| !excluded.contains(f));
         }
+        if let Some(types) = &self.types {
+            files.retain(|f| match &self.dir {
+                Some(dir) => FileKind::matches_all(types, &Path::new(dir).join(f)),
+                None => FileKind::matches_all(types, f),
+            });
+        }
         Ok(files)
     }
 
@@ -229,7 +442,12 @@ impl Step {
         files_in_contention: &HashSet<PathBuf>,
     ) -> Result<Vec<StepJob>> {
         let files = self.filter_files(files)?;
-        if files.is_empty() && (self.glob.is_some() || self.dir.is_some() || self.exclude.is_some())
+        if files.is_empty()
+            && (self.glob.is_some()
+                || self.file_types.is_some()
+                || self.dir.is_some()
+                || self.exclude.is_some()
+                || self.types.is_some())
         {
             debug!("{self}: no file matches for step");
             return Ok(Default::default());
@@ -256,7 +474,10 @@ impl Step {
         };
         for job in jobs.iter_mut().filter(|j| j.check_first) {
             // only set check_first if there are any files in contention
-            job.check_first = job.files.iter().any(|f| files_in_contention.contains(f));
+            job.check_first = job.files.iter().any(|f| match &self.dir {
+                Some(dir) => files_in_contention.contains(&Path::new(dir).join(f)),
+                None => files_in_contention.contains(f),
+            });
         }
         Ok(jobs)
     }
@@ -265,64 +486,80 @@ impl Step {
         &self,
         ctx: Arc<StepContext>,
         semaphore: Option<OwnedSemaphorePermit>,
+    ) -> Result<()> {
+        let result = self.run_jobs(ctx.clone(), semaphore).await;
+        let results = ctx.hook_ctx.results.for_step(&self.name);
+        ctx.hook_ctx.reporter.step_finished(&self.name, &results);
+        result
+    }
+
+    async fn run_jobs(
+        &self,
+        ctx: Arc<StepContext>,
+        semaphore: Option<OwnedSemaphorePermit>,
     ) -> Result<()> {
         let semaphore = self.wait_for_depends(&ctx, semaphore).await?;
         let files = ctx.hook_ctx.files();
         let ctx = Arc::new(ctx);
-        let mut jobs = self.build_step_jobs(
+        let jobs = self.build_step_jobs(
             &files,
             ctx.hook_ctx.run_type,
             &ctx.hook_ctx.files_in_contention.lock().unwrap(),
         )?;
+        let had_jobs = !jobs.is_empty();
+        // the cache is keyed on the working tree contents which aren't what gets checked
+        let is_staged = |f: &PathBuf| {
+            ctx.hook_ctx
+                .staged_for_check()
+                .is_some_and(|s| s.contains(f, self.dir.as_deref()))
+        };
+        let mut uncached = vec![];
+        for mut job in jobs {
+            if let Some(cache) = self.result_cache(&ctx, &job)? {
+                job.files.retain(|f| !cache.contains(f) || is_staged(f));
+                if job.files.is_empty() {
+                    continue;
+                }
+            }
+            uncached.push(job);
+        }
+        let mut jobs = uncached;
         if let Some(job) = jobs.first_mut() {
             job.semaphore = Some(semaphore);
         } else {
             ctx.depends.mark_done(&self.name)?;
-            debug!("{self}: no jobs to run");
+            let reason = if had_jobs {
+                debug!("{self}: all files passed previously");
+                if !self.hide {
+                    ctx.progress.prop("message", "cached");
+                    ctx.progress.set_status(ProgressStatus::Done);
+                }
+                SkipReason::Cached
+            } else {
+                debug!("{self}: no jobs to run");
+                SkipReason::NoFiles
+            };
+            ctx.hook_ctx
+                .results
+                .add(JobResult::skipped(&self.name, reason));
             return Ok(());
         }
         ctx.set_jobs_total(jobs.len());
+        let jobs_total = jobs.len();
         let mut set = tokio::task::JoinSet::new();
-        for job in jobs {
+        for (i, job) in jobs.into_iter().enumerate() {
             let ctx = ctx.clone();
             let step = self.clone();
-            let mut job = job;
+            let job_name = (jobs_total > 1).then(|| format!("{}/{jobs_total}", i + 1));
             set.spawn(async move {
                 ctx.hook_ctx.inc_total_jobs(1);
-                if job.check_first {
-                    let prev_run_type = job.run_type;
-                    job.run_type = RunType::Check(step.check_type());
-                    debug!("{step}: running check step first due to fix step contention");
-                    match step.run(&ctx, &mut job).await {
-                        Ok(()) => {
-                            debug!("{step}: successfully ran check step first");
-                            return Ok(());
-                        }
-                        Err(e) => {
-                            if let Some(Error::CheckListFailed { source, stdout }) =
-                                e.downcast_ref::<Error>()
-                            {
-                                debug!("{step}: failed check step first: {source}");
-                                let filtered_files: HashSet<PathBuf> =
-                                    stdout.lines().map(|p| try_canonicalize(&PathBuf::from(p))).collect();
-                                let files: IndexSet<PathBuf> = job.files.into_iter().filter(|f| {
-                                    filtered_files.contains(&try_canonicalize(f))
-                                }).collect();
-                                let canonicalized_files: IndexSet<PathBuf> = files.iter().map(try_canonicalize).collect();
-                                for f in filtered_files.into_iter().filter(|f| !canonicalized_files.contains(f)) {
-                                    warn!("{step}: file in check_list_files not found in original files: {}", f.display());
-                                }
-                                job.files = files.into_iter().collect();
-                            }
-                            debug!("{step}: failed check step first: {e}");
-                        }
-                    }
-                    job.run_type = prev_run_type;
-                }
-                let result = step.run(&ctx, &mut job).await;
-                if let Err(err) = &result {
-                    job.status_errored(&ctx, format!("{err}")).await?;
-                }
+                let start = Instant::now();
+                let name = match &job_name {
+                    Some(job_name) => format!("{step} [{job_name}]"),
+                    None => step.name.clone(),
+                };
+                let result = step.run_job(&ctx, job, job_name).await;
+                ctx.hook_ctx.timings.record(TimingKind::Job, name, start);
                 result
             });
         }
@@ -369,8 +606,30 @@ impl Step {
             if !stage.is_empty() {
                 let status = ctx.hook_ctx.git.lock().await.status(Some(stage))?;
                 let files = status.unstaged_files.into_iter().collect_vec();
+                let git = ctx.hook_ctx.git.lock().await;
+                // only the fixes to partially staged files are staged, not their unstaged changes
+                let (partially_staged, add): (Vec<_>, Vec<_>) =
+                    files.iter().cloned().partition(|f| {
+                        ctx.hook_ctx
+                            .staged
+                            .as_ref()
+                            .is_some_and(|s| s.contains(f, None))
+                    });
+                if let Some(staged) = &ctx.hook_ctx.staged {
+                    for file in &partially_staged {
+                        if !staged.stage_fixes(&git, file)? {
+                            warn!(
+                                "{self}: fixes to {} overlap its unstaged changes so they were not staged",
+                                file.display()
+                            );
+                        }
+                    }
+                }
+                if !add.is_empty() {
+                    git.add(&add)?;
+                }
+                drop(git);
                 if !files.is_empty() {
-                    ctx.hook_ctx.git.lock().await.add(&files)?;
                     ctx.add_files(&files);
                 }
             }
@@ -380,6 +639,177 @@ impl Step {
         Ok(())
     }
 
+    /// Runs a single job, including its check_first run and any retries
+    async fn run_job(
+        &self,
+        ctx: &StepContext,
+        mut job: StepJob,
+        job_name: Option<String>,
+    ) -> Result<()> {
+        let start = Instant::now();
+        if job.check_first {
+            let prev_run_type = job.run_type;
+            job.run_type = RunType::Check(self.check_type());
+            debug!("{self}: running check step first due to fix step contention");
+            match self.run(ctx, &mut job).await {
+                Ok(()) => {
+                    debug!("{self}: successfully ran check step first");
+                    self.add_result(ctx, &job, job_name, start, None);
+                    return Ok(());
+                }
+                Err(e) => {
+                    if let Some(Error::CheckListFailed { source, stdout }) =
+                        e.downcast_ref::<Error>()
+                    {
+                        debug!("{self}: failed check step first: {source}");
+                        let filtered_files: HashSet<PathBuf> = stdout
+                            .lines()
+                            .map(|p| try_canonicalize(&PathBuf::from(p)))
+                            .collect();
+                        let files: IndexSet<PathBuf> = job
+                            .files
+                            .into_iter()
+                            .filter(|f| filtered_files.contains(&try_canonicalize(f)))
+                            .collect();
+                        let canonicalized_files: IndexSet<PathBuf> =
+                            files.iter().map(try_canonicalize).collect();
+                        for f in filtered_files
+                            .into_iter()
+                            .filter(|f| !canonicalized_files.contains(f))
+                        {
+                            warn!(
+                                "{self}: file in check_list_files not found in original files: {}",
+                                f.display()
+                            );
+                        }
+                        job.files = files.into_iter().collect();
+                    }
+                    debug!("{self}: failed check step first: {e}");
+                }
+            }
+            job.run_type = prev_run_type;
+        }
+        let mut result = self.run(ctx, &mut job).await;
+        while let Err(err) = &result {
+            if !job.will_retry() || ctx.hook_ctx.failed.is_cancelled() {
+                break;
+            }
+            let output = error::script_failed(err)
+                .map(|e| e.2.trim_end())
+                .filter(|output| !output.is_empty())
+                .map(|output| format!("\n{output}"))
+                .unwrap_or_default();
+            warn!(
+                "{self}: attempt {}/{} failed: {err}{output}",
+                job.attempt,
+                self.retries + 1,
+            );
+            // releases the job's locks while waiting
+            job.status = StepJobStatus::Pending;
+            tokio::time::sleep(self.retry_delay).await;
+            job.attempt += 1;
+            result = self.run(ctx, &mut job).await;
+        }
+        self.add_result(ctx, &job, job_name.clone(), start, result.as_ref().err());
+        if let Err(err) = &result {
+            let summary = &ctx.hook_ctx.summary;
+            if self.is_warning(err) {
+                summary.add(Severity::Warning, &self.name, job_name, err);
+                ctx.decrement_job_count();
+                job.status_warned(ctx, summary::short_message(err))?;
+                return Ok(());
+            }
+            // jobs killed due to another step failing aren't failures themselves
+            if !ctx.hook_ctx.failed.is_cancelled() {
+                summary.add(Severity::Error, &self.name, job_name, err);
+            }
+            job.status_errored(ctx, format!("{err}")).await?;
+        }
+        result
+    }
+
+    fn add_result(
+        &self,
+        ctx: &StepContext,
+        job: &StepJob,
+        job_name: Option<String>,
+        start: Instant,
+        err: Option<&eyre::Report>,
+    ) {
+        let outcome = match (err, &job.status) {
+            (Some(err), _) if self.is_warning(err) => Outcome::Warned(summary::short_message(err)),
+            (Some(_), _) if ctx.hook_ctx.failed.is_cancelled() => {
+                Outcome::Skipped("cancelled due to a previous failure".to_string())
+            }
+            (Some(err), _) => Outcome::Failed(summary::short_message(err)),
+            (None, StepJobStatus::Skipped(reason)) => Outcome::Skipped(reason.clone()),
+            (None, _) => Outcome::Passed,
+        };
+        let mut result = JobResult::new(&self.name, job_name, outcome, start.elapsed())
+            .with_output(job.output.as_ref());
+        if let Some(err) = err {
+            result = result.with_error(err);
+        }
+        if let (Some(parser), Some(output)) = (self.output_parser(), &job.output) {
+            // JSON goes to stdout but tools printing `file:line:col: message` often use stderr
+            let output = match parser {
+                OutputParser::Regex => &output.combined_output,
+                _ => &output.stdout,
+            };
+            match parser.parse(
+                self.output_pattern.as_deref(),
+                output,
+                self.dir.as_deref().map(Path::new),
+            ) {
+                Ok(diagnostics) => result.diagnostics = Some(diagnostics),
+                Err(err) => warn!("{self}: failed to parse output with {parser}: {err}"),
+            }
+        }
+        if matches!(result.outcome, Outcome::Passed) && matches!(job.run_type, RunType::Check(_)) {
+            let cacheable = job
+                .files
+                .iter()
+                .filter(|f| {
+                    !ctx.hook_ctx
+                        .staged_for_check()
+                        .is_some_and(|s| s.contains(f, self.dir.as_deref()))
+                })
+                .cloned()
+                .collect_vec();
+            let cached = self
+                .result_cache(ctx, job)
+                .and_then(|cache| cache.map(|c| c.add(&cacheable)).transpose());
+            if let Err(err) = cached {
+                warn!("{self}: failed to cache result: {err}");
+            }
+        }
+        ctx.hook_ctx.results.add(result);
+    }
+
+    /// Only steps with `cache` set whose check command reads `{{files}}` are cached since the
+    /// result of other commands can depend on more than the files the step matched
+    fn result_cache(&self, ctx: &StepContext, job: &StepJob) -> Result<Option<ResultCache>> {
+        if !self.cache || !ctx.hook_ctx.cache || !matches!(job.run_type, RunType::Check(_)) {
+            return Ok(None);
+        }
+        let Some(script) = self.run_cmd(job.run_type) else {
+            return Ok(None);
+        };
+        if !tera::uses_var(&script.to_string(), "files")? {
+            return Ok(None);
+        }
+        // rendered without the files since each entry is keyed on its own file
+        let mut tctx = job.tctx(&ctx.hook_ctx.tctx);
+        tctx.with_files::<PathBuf>(self.shell_type(), &[]);
+        let run = self.render_run_cmd(job.run_type, &tctx)?;
+        let env = self
+            .env
+            .iter()
+            .map(|(k, v)| Ok((k.as_str(), tera::render(v, &tctx)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(ResultCache::new(self, &run, &env)?))
+    }
+
     async fn wait_for_depends(
         &self,
         ctx: &StepContext,
@@ -394,32 +824,52 @@ impl Step {
         }
         match semaphore {
             Some(semaphore) => Ok(semaphore),
-            None => Ok(ctx.hook_ctx.semaphore().await),
+            None => {
+                let start = Instant::now();
+                let semaphore = ctx.hook_ctx.semaphore().await;
+                ctx.hook_ctx
+                    .timings
+                    .record(TimingKind::SemaphoreWait, &self.name, start);
+                Ok(semaphore)
+            }
         }
     }
 
     pub(crate) async fn run(&self, ctx: &StepContext, job: &mut StepJob) -> Result<()> {
         if ctx.hook_ctx.failed.is_cancelled() {
             trace!("{self}: skipping step due to previous failure");
+            job.status = StepJobStatus::Skipped("cancelled due to a previous failure".to_string());
             return Ok(());
         }
-        if let Some(condition) = &self.condition {
-            let val = EXPR_ENV.eval(condition, &ctx.hook_ctx.expr_ctx())?;
-            trace!("{self}: condition: {condition} = {val}");
-            if val == expr::Value::Bool(false) {
-                return Ok(());
-            }
+        if !self.condition_met(&ctx.hook_ctx.expr_ctx())? {
+            let condition = self.condition.clone().unwrap_or_default();
+            job.status = StepJobStatus::Skipped(SkipReason::Condition(condition).to_string());
+            return Ok(());
+        }
+        if job.attempt == 1 || job.progress.is_none() {
+            job.progress = Some(job.build_progress(ctx));
         }
-        job.progress = Some(job.build_progress(ctx));
         job.status = StepJobStatus::Pending;
         let semaphore = if let Some(semaphore) = job.semaphore.take() {
             semaphore
         } else {
-            ctx.hook_ctx.semaphore().await
+            let start = Instant::now();
+            let semaphore = ctx.hook_ctx.semaphore().await;
+            ctx.hook_ctx
+                .timings
+                .record(TimingKind::SemaphoreWait, &self.name, start);
+            semaphore
         };
         job.status_start(ctx, semaphore).await?;
         let mut tctx = job.tctx(&ctx.hook_ctx.tctx);
-        tctx.with_globs(self.glob.as_ref().unwrap_or(&vec![]));
+        let staged = ctx
+            .hook_ctx
+            .staged_for_check()
+            .filter(|_| matches!(job.run_type, RunType::Check(_)));
+        if let Some(staged) = staged {
+            let files = staged.replace(&job.files, self.dir.as_deref());
+            tctx.with_files(self.shell_type(), &files);
+        }
         let file_msg = |files: &[PathBuf]| {
             format!(
                 "{} file{}",
@@ -427,17 +877,16 @@ impl Step {
                 if files.len() == 1 { "" } else { "s" }
             )
         };
-        let Some(mut run) = self.run_cmd(job.run_type).map(|s| s.to_string()) else {
-            eyre::bail!("{self}: no run command");
+        let attempt_msg = if job.attempt > 1 {
+            format!("attempt {}/{} – ", job.attempt, self.retries + 1)
+        } else {
+            String::new()
         };
-        if let Some(prefix) = &self.prefix {
-            run = format!("{prefix} {run}");
-        }
-        let run = tera::render(&run, &tctx).unwrap();
+        let run = self.render_run_cmd(job.run_type, &tctx)?;
         job.progress.as_ref().unwrap().prop(
             "message",
             &format!(
-                "{} – {} – {}",
+                "{attempt_msg}{} – {} – {}",
                 file_msg(&job.files),
                 self.glob.as_ref().unwrap_or(&vec![]).join(" "),
                 run
@@ -452,25 +901,23 @@ impl Step {
         let mut cmd = if let Some(shell) = &self.shell {
             let shell = shell.to_string();
             let shell = shell.split_whitespace().collect_vec();
-            let mut cmd = CmdLineRunner::new(shell[0]);
+            let mut cmd = JobCmd::new(shell[0]);
             for arg in shell[1..].iter() {
                 cmd = cmd.arg(arg);
             }
             cmd
         } else {
-            CmdLineRunner::new("sh").arg("-o").arg("errexit").arg("-c")
+            JobCmd::new("sh").arg("-o").arg("errexit").arg("-c")
         };
         cmd = cmd
             .arg(&run)
-            .with_pr(job.progress.as_ref().unwrap().clone())
-            .with_cancel_token(ctx.hook_ctx.failed.clone())
-            .show_stderr_on_error(false);
+            .with_cancel_token(ctx.hook_ctx.failed.child_token());
+        if ctx.hook_ctx.reporter.live_output() {
+            cmd = cmd.with_pr(job.progress.as_ref().unwrap().clone());
+        }
         if self.interactive {
             clx::progress::pause();
-            cmd = cmd
-                .stdin(Stdio::inherit())
-                .stdout(Stdio::inherit())
-                .stderr(Stdio::inherit());
+            cmd = cmd.interactive(true);
         }
         if let Some(dir) = &self.dir {
             cmd = cmd.current_dir(dir);
@@ -479,16 +926,44 @@ impl Step {
             let value = tera::render(value, &tctx)?;
             cmd = cmd.env(key, value);
         }
-        match cmd.execute().await {
-            Ok(_) => {}
-            Err(err) => {
+        let Some(result) = cmd.execute(self.timeout).await else {
+            debug!(
+                "{self}: timed out after {:?}",
+                self.timeout.unwrap_or_default()
+            );
+            if self.interactive {
+                clx::progress::resume();
+            }
+            if !job.will_retry() && self.on_timeout == OnTimeout::Error && !self.allow_failure {
+                ctx.progress.set_status(ProgressStatus::Failed);
+            }
+            return Err(Error::TimedOut(self.timeout.unwrap_or_default()))?;
+        };
+        // show the files checked instead of where their staged contents were written
+        let restore = |s: &str| match staged {
+            Some(staged) => staged.restore(s, self.dir.as_deref()),
+            None => s.to_string(),
+        };
+        match result {
+            Ok(mut output) => {
+                output.stdout = restore(&output.stdout);
+                output.stderr = restore(&output.stderr);
+                output.combined_output = restore(&output.combined_output);
+                job.output = Some(output);
+            }
+            Err(mut err) => {
                 if self.interactive {
                     clx::progress::resume();
                 }
-                if let ensembler::Error::ScriptFailed(e) = &err {
+                if let ensembler::Error::ScriptFailed(e) = &mut err {
+                    e.1 = e.1.iter().map(|arg| restore(arg)).collect();
+                    e.2 = restore(&e.2);
+                    e.3.stdout = restore(&e.3.stdout);
+                    e.3.stderr = restore(&e.3.stderr);
+                    e.3.combined_output = restore(&e.3.combined_output);
+                    job.output = Some(e.3.clone());
                     if let RunType::Check(CheckType::ListFiles) = job.run_type {
-                        let result = &e.3;
-                        let stdout = result.stdout.clone();
+                        let stdout = e.3.stdout.clone();
                         return Err(Error::CheckListFailed {
                             source: eyre!("{}", err),
                             stdout,
@@ -497,10 +972,10 @@ impl Step {
                 }
                 if job.check_first && matches!(job.run_type, RunType::Check(_)) {
                     ctx.progress.set_status(ProgressStatus::Warn);
-                } else {
+                } else if !job.will_retry() && !self.allow_failure {
                     ctx.progress.set_status(ProgressStatus::Failed);
                 }
-                return Err(err).wrap_err(run);
+                return Err(err).wrap_err(restore(&run));
             }
         }
         if self.interactive {
@@ -511,6 +986,30 @@ impl Step {
         Ok(())
     }
 
+    /// `output_pattern` implies the regex parser
+    pub fn output_parser(&self) -> Option<OutputParser> {
+        self.output_parser
+            .or(self.output_pattern.as_ref().map(|_| OutputParser::Regex))
+    }
+
+    /// Whether `err` should be shown as a warning instead of failing the hook
+    fn is_warning(&self, err: &eyre::Report) -> bool {
+        self.allow_failure
+            || (self.on_timeout == OnTimeout::Warn
+                && matches!(err.downcast_ref::<Error>(), Some(Error::TimedOut(_))))
+    }
+
+    /// Renders the command for `run_type` with the step's prefix applied
+    pub(crate) fn render_run_cmd(&self, run_type: RunType, tctx: &tera::Context) -> Result<String> {
+        let Some(mut run) = self.run_cmd(run_type).map(|s| s.to_string()) else {
+            eyre::bail!("{self}: no run command");
+        };
+        if let Some(prefix) = &self.prefix {
+            run = format!("{prefix} {run}");
+        }
+        tera::render(&run, tctx)
+    }
+
     pub fn shell_type(&self) -> ShellType {
         let shell = self
             .shell
@@ -553,11 +1052,10 @@ impl ShellType {
         }
     }
 }
-fn is_profile_enabled(
-    name: &str,
+fn profile_skip_reason(
     enabled: Option<IndexSet<String>>,
     disabled: Option<IndexSet<String>>,
-) -> bool {
+) -> Option<SkipReason> {
     let settings = Settings::get();
     if let Some(enabled) = enabled {
         let missing_profiles = enabled
@@ -565,8 +1063,7 @@ fn is_profile_enabled(
             .collect::<Vec<_>>();
         if !missing_profiles.is_empty() {
             let missing_profiles = missing_profiles.iter().join(", ");
-            debug!("{name}: skipping step due to missing profile: {missing_profiles}");
-            return false;
+            return Some(SkipReason::MissingProfile(missing_profiles));
         }
         let disabled_profiles = settings
             .disabled_profiles
@@ -574,8 +1071,7 @@ fn is_profile_enabled(
             .collect_vec();
         if !disabled_profiles.is_empty() {
             let disabled_profiles = disabled_profiles.iter().join(", ");
-            debug!("{name}: skipping step due to disabled profile: {disabled_profiles}");
-            return false;
+            return Some(SkipReason::DisabledProfile(disabled_profiles));
         }
     }
     if let Some(disabled) = disabled {
@@ -584,11 +1080,37 @@ fn is_profile_enabled(
             .collect::<Vec<_>>();
         if !disabled_profiles.is_empty() {
             let disabled_profiles = disabled_profiles.iter().join(", ");
-            debug!("{name}: skipping step due to disabled profile: {disabled_profiles}");
-            return false;
+            return Some(SkipReason::DisabledProfile(disabled_profiles));
+        }
+    }
+    None
+}
+
+/// Why a step is not going to run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", content = "detail", rename_all = "snake_case")]
+pub enum SkipReason {
+    NoRunCommand,
+    SkipSteps,
+    MissingProfile(String),
+    DisabledProfile(String),
+    Condition(String),
+    NoFiles,
+    Cached,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::NoRunCommand => write!(f, "no available run type"),
+            SkipReason::SkipSteps => write!(f, "HK_SKIP_STEPS"),
+            SkipReason::MissingProfile(p) => write!(f, "missing profile: {p}"),
+            SkipReason::DisabledProfile(p) => write!(f, "disabled profile: {p}"),
+            SkipReason::Condition(c) => write!(f, "condition: {c}"),
+            SkipReason::NoFiles => write!(f, "no file matches"),
+            SkipReason::Cached => write!(f, "already passed on these files"),
         }
     }
-    true
 }
 
 pub static EXPR_CTX: LazyLock<expr::Context> = LazyLock::new(expr::Context::default);
@@ -637,6 +1159,15 @@ impl FromStr for Script {
     }
 }
 
+impl Script {
+    /// Every OS-specific variant of the script that is defined
+    pub fn all(&self) -> impl Iterator<Item = &String> {
+        [&self.linux, &self.macos, &self.windows, &self.other]
+            .into_iter()
+            .flatten()
+    }
+}
+
 impl Display for Script {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let other = self.other.as_deref().unwrap_or_default();
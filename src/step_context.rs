@@ -21,6 +21,7 @@ pub enum StepStatus {
     Started,
     Aborted,
     Finished,
+    Warned(String),
     Errored(String),
 }
 
@@ -50,6 +51,7 @@ impl StepContext {
             StepStatus::Started
             | StepStatus::Aborted
             | StepStatus::Finished
+            | StepStatus::Warned(_)
             | StepStatus::Errored(_) => {}
         }
     }
@@ -61,14 +63,32 @@ impl StepContext {
                 *status = StepStatus::Aborted;
                 self.update_progress();
             }
-            StepStatus::Aborted | StepStatus::Finished | StepStatus::Errored(_) => {}
+            StepStatus::Aborted
+            | StepStatus::Finished
+            | StepStatus::Warned(_)
+            | StepStatus::Errored(_) => {}
         }
     }
 
-    pub fn status_errored(&self, err: &str) {
+    pub fn status_warned(&self, msg: &str) {
         let mut status = self.status.lock().unwrap();
         match &*status {
             StepStatus::Pending | StepStatus::Started => {
+                *status = StepStatus::Warned(msg.to_string());
+                drop(status);
+                self.update_progress();
+            }
+            StepStatus::Aborted
+            | StepStatus::Finished
+            | StepStatus::Warned(_)
+            | StepStatus::Errored(_) => {}
+        }
+    }
+
+    pub fn status_errored(&self, err: &str) {
+        let mut status = self.status.lock().unwrap();
+        match &*status {
+            StepStatus::Pending | StepStatus::Started | StepStatus::Warned(_) => {
                 *status = StepStatus::Errored(err.to_string());
                 drop(status);
                 self.update_progress();
@@ -88,6 +108,7 @@ impl StepContext {
             StepStatus::Pending
             | StepStatus::Aborted
             | StepStatus::Finished
+            | StepStatus::Warned(_)
             | StepStatus::Errored(_) => {}
         }
     }
@@ -126,6 +147,11 @@ impl StepContext {
             StepStatus::Finished => {
                 self.progress.set_status(ProgressStatus::Done);
             }
+            StepStatus::Warned(msg) => {
+                self.progress.set_status(ProgressStatus::Warn);
+                self.progress
+                    .prop("message", &style::eyellow(msg).to_string());
+            }
             StepStatus::Errored(_err) => {
                 self.progress.set_status(ProgressStatus::Failed);
                 self.progress
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
-use crate::Result;
+use crate::{Result, step::Step};
+use indexmap::{IndexMap, IndexSet};
 use tokio::sync::watch;
 
 pub struct StepDepends {
@@ -26,6 +27,7 @@ impl StepDepends {
 
     pub async fn wait_for(&self, step: &str) -> Result<()> {
         let Some((_tx, rx)) = self.depends.get(step) else {
+            // steps in earlier groups have already finished and skipped steps will never run
             return Ok(());
         };
         let mut rx = rx.clone();
@@ -41,3 +43,43 @@ impl StepDepends {
         Ok(())
     }
 }
+
+/// Returns the first dependency cycle found among `steps`, e.g.: `["a", "b", "a"]`
+///
+/// Dependencies on steps that are not in `steps` are ignored.
+pub fn find_cycle(steps: &IndexMap<String, Step>) -> Option<Vec<String>> {
+    fn visit<'a>(
+        name: &'a str,
+        steps: &'a IndexMap<String, Step>,
+        path: &mut IndexSet<&'a str>,
+        done: &mut IndexSet<&'a str>,
+    ) -> Option<Vec<String>> {
+        if done.contains(name) {
+            return None;
+        }
+        if let Some(idx) = path.get_index_of(name) {
+            let mut cycle = path
+                .iter()
+                .skip(idx)
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>();
+            cycle.push(name.to_string());
+            return Some(cycle);
+        }
+        path.insert(name);
+        for dep in &steps.get(name)?.depends {
+            if steps.contains_key(dep) {
+                if let Some(cycle) = visit(dep, steps, path, done) {
+                    return Some(cycle);
+                }
+            }
+        }
+        path.pop();
+        done.insert(name);
+        None
+    }
+    let mut done = IndexSet::new();
+    steps
+        .keys()
+        .find_map(|name| visit(name, steps, &mut IndexSet::new(), &mut done))
+}
@@ -4,15 +4,23 @@ use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    Result, glob, hook::StepOrGroup, settings::Settings, step::RunType, step_context::StepContext,
+    Result,
+    hook::StepOrGroup,
+    plan::{GroupPlan, StepPlan},
+    settings::Settings,
+    step::{RunType, SkipReason},
+    step_context::StepContext,
     step_depends::StepDepends,
+    timings::TimingKind,
 };
 use crate::{hook::HookContext, step::Step};
+use itertools::Itertools;
 
 use std::{
     collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
+    time::Instant,
 };
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize, Eq, PartialEq)]
@@ -80,6 +88,15 @@ impl StepGroup {
             .collect()
     }
 
+    /// Returns a description of every problem found in the group's steps
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        for step in self.steps.values() {
+            errors.extend(step.validate().into_iter().map(|e| format!("{step}: {e}")));
+        }
+        errors
+    }
+
     pub fn build_group_progress(&self, name: &str) -> Arc<ProgressJob> {
         ProgressJobBuilder::new()
             .body("group: {{group}}")
@@ -87,12 +104,48 @@ impl StepGroup {
             .start()
     }
 
-    pub async fn plan(self) -> Result<()> {
-        for step_name in self.steps.keys() {
-            info!("step: {step_name} –");
-            todo!("list files and run types like check-first");
-        }
-        Ok(())
+    pub fn plan(&self, hook_ctx: &HookContext) -> Result<GroupPlan> {
+        let run_type = hook_ctx.run_type;
+        let files = hook_ctx.files();
+        let runnable = self
+            .steps
+            .values()
+            .filter(|s| s.skip_reason(run_type).is_none())
+            .cloned()
+            .map(|s| (s.name.clone(), s))
+            .collect();
+        let runnable = Self {
+            steps: runnable,
+            ..self.clone()
+        };
+        let files_in_contention = runnable.files_in_contention(run_type, &files)?;
+        let expr_ctx = hook_ctx.expr_ctx();
+        let steps = self
+            .steps
+            .values()
+            .map(|step| {
+                let skip = match step.skip_reason(run_type) {
+                    Some(reason) => Some(reason),
+                    None if !step.condition_met(&expr_ctx)? => Some(SkipReason::Condition(
+                        step.condition.clone().unwrap_or_default(),
+                    )),
+                    None => None,
+                };
+                if let Some(skip) = skip {
+                    return Ok(StepPlan::skipped(step, skip));
+                }
+                let jobs = step.build_step_jobs(&files, run_type, &files_in_contention)?;
+                if jobs.is_empty() {
+                    return Ok(StepPlan::skipped(step, SkipReason::NoFiles));
+                }
+                StepPlan::new(step, &jobs, &hook_ctx.tctx)
+            })
+            .collect::<Result<_>>()?;
+        Ok(GroupPlan {
+            name: self.name.clone(),
+            files_in_contention: files_in_contention.into_iter().sorted().collect(),
+            steps,
+        })
     }
 
     pub async fn run(&self, ctx: StepGroupContext) -> Result<()> {
@@ -124,7 +177,8 @@ impl StepGroup {
                 )
             })
             .collect();
-        *ctx.hook_ctx.files_in_contention.lock().unwrap() = self.files_in_contention(&ctx)?;
+        *ctx.hook_ctx.files_in_contention.lock().unwrap() =
+            self.files_in_contention(ctx.hook_ctx.run_type, &ctx.hook_ctx.files())?;
         if self.steps.values().any(|j| j.check_first) {
         } else {
             *ctx.hook_ctx.files_in_contention.lock().unwrap() = Default::default();
@@ -143,7 +197,9 @@ impl StepGroup {
                 let step_ctx = step_ctx.clone();
                 let hook_ctx = ctx.hook_ctx.clone();
                 async move {
+                    let start = Instant::now();
                     let result = step.run_all_jobs(step_ctx.clone(), semaphore).await;
+                    hook_ctx.timings.record(TimingKind::Step, &step.name, start);
                     if let Err(err) = &result {
                         step_ctx.status_errored(&err.to_string());
                     }
@@ -186,11 +242,14 @@ impl StepGroup {
         result
     }
 
-    fn files_in_contention(&self, ctx: &StepGroupContext) -> Result<HashSet<PathBuf>> {
-        if ctx.hook_ctx.run_type != RunType::Fix || !self.steps.values().any(|j| j.check_first) {
+    fn files_in_contention(
+        &self,
+        run_type: RunType,
+        files: &[PathBuf],
+    ) -> Result<HashSet<PathBuf>> {
+        if run_type != RunType::Fix || !self.steps.values().any(|j| j.check_first) {
             return Ok(Default::default());
         }
-        let files = ctx.hook_ctx.files();
         let step_map: HashMap<&str, &Step> = self
             .steps
             .values()
@@ -200,7 +259,14 @@ impl StepGroup {
             .steps
             .values()
             .map(|step| {
-                let files = glob::get_matches(step.glob.as_ref().unwrap_or(&vec![]), &files)?;
+                let files = step
+                    .filter_files(files)?
+                    .into_iter()
+                    .map(|f| match &step.dir {
+                        Some(dir) => Path::new(dir).join(f),
+                        None => f,
+                    })
+                    .collect();
                 Ok((step.name.as_str(), files))
             })
             .collect::<Result<_>>()?;
@@ -1,10 +1,14 @@
 use crate::{Result, file_rw_locks::Flocks};
 use clx::progress::{ProgressJob, ProgressJobBuilder, ProgressJobDoneBehavior, ProgressStatus};
+use ensembler::CmdResult;
 use itertools::Itertools;
 use tokio::sync::OwnedSemaphorePermit;
 
-use crate::{env, step::Step, step_context::StepContext, step_locks::StepLocks, tera};
-use std::{path::PathBuf, sync::Arc};
+use crate::{
+    settings::Settings, step::Step, step_context::StepContext, step_locks::StepLocks, tera,
+    timings::TimingKind, ui::style,
+};
+use std::{path::PathBuf, sync::Arc, time::Instant};
 
 use crate::step::RunType;
 
@@ -22,7 +26,11 @@ pub struct StepJob {
     pub check_first: bool,
     pub progress: Option<Arc<ProgressJob>>,
     pub semaphore: Option<OwnedSemaphorePermit>,
-    workspace_indicator: Option<PathBuf>,
+    pub workspace_indicator: Option<PathBuf>,
+    /// starts at 1 and is incremented each time the job is retried
+    pub attempt: usize,
+    /// output of the last command run for this job
+    pub output: Option<CmdResult>,
 
     pub status: StepJobStatus,
 }
@@ -32,6 +40,7 @@ pub enum StepJobStatus {
     Pending,
     Started(StepLocks),
     Finished,
+    Skipped(String),
     Errored(String),
 }
 
@@ -41,7 +50,7 @@ impl StepJob {
             files,
             run_type,
             workspace_indicator: None,
-            check_first: *env::HK_CHECK_FIRST
+            check_first: Settings::get().check_first
                 && step.check_first
                 && step.fix.is_some()
                 && (step.check.is_some()
@@ -52,9 +61,16 @@ impl StepJob {
             status: StepJobStatus::Pending,
             progress: None,
             semaphore: None,
+            attempt: 1,
+            output: None,
         }
     }
 
+    /// Whether the job will be run again if the current attempt fails
+    pub fn will_retry(&self) -> bool {
+        self.attempt <= self.step.retries
+    }
+
     pub fn with_workspace_indicator(mut self, workspace_indicator: PathBuf) -> Self {
         let workspace_dir = workspace_indicator.parent().unwrap();
         self.files = self
@@ -69,6 +85,7 @@ impl StepJob {
 
     pub fn tctx(&self, base: &tera::Context) -> tera::Context {
         let mut tctx = base.clone();
+        tctx.with_globs(self.step.glob.as_ref().unwrap_or(&vec![]));
         tctx.with_files(self.step.shell_type(), &self.files);
         if let Some(workspace_indicator) = &self.workspace_indicator {
             tctx.with_workspace_indicator(workspace_indicator);
@@ -84,9 +101,11 @@ impl StepJob {
                 // TODO: truncate properly
                 "{{spinner()}} {% if ensembler_cmd %}{{ensembler_cmd | flex}}\n{{ensembler_stdout | flex}}{% else %}{{message | flex}}{% endif %}"
             )
-            .body_text(Some(
-                "{% if ensembler_stdout %}  {{name}} – {{ensembler_stdout}}{% elif message %}{{spinner()}} {{name}} – {{message}}{% endif %}".to_string(),
-            ))
+            .body_text(Some(if ctx.hook_ctx.reporter.live_output() {
+                "{% if ensembler_stdout %}  {{name}} – {{ensembler_stdout}}{% elif message %}{{spinner()}} {{name}} – {{message}}{% endif %}"
+            } else {
+                ""
+            }))
             .on_done(ProgressJobDoneBehavior::Hide)
             .build();
         ctx.progress.add(job)
@@ -104,7 +123,11 @@ impl StepJob {
             }
             _ => unreachable!("invalid status: {:?}", self.status),
         }
+        let start = Instant::now();
         let flocks = self.flocks(ctx).await;
+        ctx.hook_ctx
+            .timings
+            .record(TimingKind::LockWait, &self.step.name, start);
         self.status = StepJobStatus::Started(StepLocks::new(flocks, semaphore));
         ctx.status_started();
         if let Some(progress) = &mut self.progress {
@@ -125,6 +148,21 @@ impl StepJob {
         Ok(())
     }
 
+    /// The job didn't succeed but shouldn't fail the hook
+    pub fn status_warned(&mut self, ctx: &StepContext, msg: String) -> Result<()> {
+        match &mut self.status {
+            StepJobStatus::Pending | StepJobStatus::Started(_) => {}
+            _ => unreachable!("invalid status: {:?}", self.status),
+        }
+        self.status = StepJobStatus::Finished;
+        if let Some(progress) = &mut self.progress {
+            progress.prop("message", &style::eyellow(&msg).to_string());
+            progress.set_status(ProgressStatus::Warn);
+        }
+        ctx.status_warned(&msg);
+        Ok(())
+    }
+
     pub async fn status_errored(&mut self, ctx: &StepContext, err: String) -> Result<()> {
         match &mut self.status {
             StepJobStatus::Pending | StepJobStatus::Started(_) => {}
@@ -161,6 +199,8 @@ impl Clone for StepJob {
             status: StepJobStatus::Pending,
             progress: self.progress.clone(),
             semaphore: None,
+            attempt: self.attempt,
+            output: self.output.clone(),
         }
     }
 }
@@ -0,0 +1,143 @@
+use std::{path::PathBuf, sync::Mutex};
+
+use indexmap::IndexMap;
+use itertools::Itertools;
+
+use crate::{Result, env, error, ui::style};
+
+/// Failures collected while running a hook which are shown once it has finished
+#[derive(Debug, Default)]
+pub struct Summary {
+    failures: Mutex<Vec<Failure>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// the step was allowed to fail
+    Warning,
+    Error,
+}
+
+#[derive(Debug)]
+struct Failure {
+    severity: Severity,
+    step: String,
+    /// e.g.: "2/3" if the step was split into multiple jobs
+    job: Option<String>,
+    command: Option<String>,
+    message: String,
+    output: String,
+    combined_output: String,
+}
+
+impl Summary {
+    pub fn add(&self, severity: Severity, step: &str, job: Option<String>, err: &eyre::Report) {
+        let script_failed = error::script_failed(err);
+        self.failures.lock().unwrap().push(Failure {
+            severity,
+            step: step.to_string(),
+            job,
+            command: script_failed.map(|e| command(&e.0, &e.1)),
+            message: short_message(err),
+            output: script_failed
+                .map(|e| e.2.trim_end().to_string())
+                .unwrap_or_default(),
+            combined_output: script_failed
+                .map(|e| console::strip_ansi_codes(&e.3.combined_output).to_string())
+                .unwrap_or_default(),
+        });
+    }
+
+    /// Prints every failure and writes each step's output to its own file under HK_STATE_DIR,
+    /// returns false if there were none
+    pub fn print(&self) -> bool {
+        let failures = self.failures.lock().unwrap();
+        if failures.is_empty() {
+            return false;
+        }
+        let mut by_step: IndexMap<&str, Vec<&Failure>> = IndexMap::new();
+        for failure in failures.iter() {
+            by_step.entry(&failure.step).or_default().push(failure);
+        }
+        let output_files = match write_output_files(&by_step) {
+            Ok(output_files) => output_files,
+            Err(err) => {
+                warn!("failed to write output files: {err:?}");
+                Default::default()
+            }
+        };
+        clx::progress::flush();
+        clx::progress::pause();
+        for (step, failures) in &by_step {
+            for failure in failures {
+                let (icon, message) = match failure.severity {
+                    Severity::Warning => (style::eyellow("▲"), style::eyellow(&failure.message)),
+                    Severity::Error => (style::ered("✗"), style::ered(&failure.message)),
+                };
+                let name = match &failure.job {
+                    Some(job) => format!("{step} [{job}]"),
+                    None => step.to_string(),
+                };
+                eprintln!("{icon} {} – {message}", style::ebold(name));
+                if let Some(command) = &failure.command {
+                    eprintln!("  {}", style::edim(command));
+                }
+                for line in failure.output.lines() {
+                    eprintln!("  {line}");
+                }
+            }
+            if let Some(path) = output_files.get(step) {
+                eprintln!("  {}", style::edim(format!("output: {}", path.display())));
+            }
+        }
+        clx::progress::resume();
+        true
+    }
+}
+
+/// e.g.: "exit code 1" or "timed out after 5s"
+pub fn short_message(err: &eyre::Report) -> String {
+    match error::script_failed(err) {
+        Some(e) => match e.3.status.code() {
+            Some(code) => format!("exit code {code}"),
+            None => "no exit status".to_string(),
+        },
+        None => err.to_string(),
+    }
+}
+
+pub fn command(bin: &str, args: &[String]) -> String {
+    let cmd = format!("{} {}", bin, args.join(" "));
+    match cmd.strip_prefix("sh -o errexit -c ") {
+        Some(cmd) => cmd.to_string(),
+        None => cmd,
+    }
+}
+
+fn write_output_files<'a>(
+    by_step: &IndexMap<&'a str, Vec<&Failure>>,
+) -> Result<IndexMap<&'a str, PathBuf>> {
+    let dir = env::HK_STATE_DIR.join("output");
+    // don't leave output from a previous run around for steps which have since been fixed
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    let mut output_files = IndexMap::new();
+    for (step, failures) in by_step {
+        let output = failures
+            .iter()
+            .filter(|f| !f.combined_output.is_empty())
+            .map(|f| match &f.command {
+                Some(command) => format!("$ {command}\n{}", f.combined_output),
+                None => f.combined_output.clone(),
+            })
+            .join("\n");
+        if output.is_empty() {
+            continue;
+        }
+        let path = dir.join(format!("{}.log", step.replace(['/', '\\'], "_")));
+        xx::file::write(&path, output)?;
+        output_files.insert(*step, path);
+    }
+    Ok(output_files)
+}
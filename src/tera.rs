@@ -3,7 +3,7 @@ use std::{path::Path, sync::LazyLock};
 use crate::{Result, step::ShellType};
 use itertools::Itertools;
 use serde::Serialize;
-use tera::Tera;
+use tera::{Tera, ast};
 
 pub fn render(input: &str, ctx: &Context) -> Result<String> {
     let mut tera = Tera::default();
@@ -11,6 +11,13 @@ pub fn render(input: &str, ctx: &Context) -> Result<String> {
     Ok(output)
 }
 
+/// Parses a template without rendering it
+pub fn parse(input: &str) -> Result<()> {
+    let mut tera = Tera::default();
+    tera.add_raw_template("validate", input)?;
+    Ok(())
+}
+
 static BASE_CONTEXT: LazyLock<tera::Context> = LazyLock::new(|| {
     let mut ctx = tera::Context::new();
     let cwd = std::env::current_dir().expect("failed to get current directory");
@@ -75,3 +82,96 @@ impl Context {
         self
     }
 }
+
+/// Whether a template reads the variable `name`, e.g.: `{{ files }}` or `{% for f in files %}`
+pub fn uses_var(input: &str, name: &str) -> Result<bool> {
+    let template = tera::Template::new("uses_var", None, input)?;
+    Ok(nodes_use_var(&template.ast, name))
+}
+
+fn nodes_use_var(nodes: &[ast::Node], name: &str) -> bool {
+    nodes.iter().any(|node| match node {
+        ast::Node::VariableBlock(_, expr) => expr_uses_var(expr, name),
+        ast::Node::Set(_, set) => expr_uses_var(&set.value, name),
+        ast::Node::FilterSection(_, section, _) => {
+            section.filter.args.values().any(|e| expr_uses_var(e, name))
+                || nodes_use_var(&section.body, name)
+        }
+        ast::Node::Block(_, block, _) => nodes_use_var(&block.body, name),
+        ast::Node::Forloop(_, forloop, _) => {
+            expr_uses_var(&forloop.container, name)
+                || nodes_use_var(&forloop.body, name)
+                || forloop
+                    .empty_body
+                    .as_ref()
+                    .is_some_and(|body| nodes_use_var(body, name))
+        }
+        ast::Node::If(cond, _) => {
+            cond.conditions
+                .iter()
+                .any(|(_, expr, body)| expr_uses_var(expr, name) || nodes_use_var(body, name))
+                || cond
+                    .otherwise
+                    .as_ref()
+                    .is_some_and(|(_, body)| nodes_use_var(body, name))
+        }
+        _ => false,
+    })
+}
+
+fn expr_uses_var(expr: &ast::Expr, name: &str) -> bool {
+    val_uses_var(&expr.val, name)
+        || expr
+            .filters
+            .iter()
+            .any(|f| f.args.values().any(|e| expr_uses_var(e, name)))
+}
+
+fn val_uses_var(val: &ast::ExprVal, name: &str) -> bool {
+    let is_var = |ident: &str| {
+        ident == name
+            || ident
+                .strip_prefix(name)
+                .is_some_and(|rest| rest.starts_with(['.', '[']))
+    };
+    match val {
+        ast::ExprVal::Ident(ident) => is_var(ident),
+        ast::ExprVal::Math(math) => {
+            expr_uses_var(&math.lhs, name) || expr_uses_var(&math.rhs, name)
+        }
+        ast::ExprVal::Logic(logic) => {
+            expr_uses_var(&logic.lhs, name) || expr_uses_var(&logic.rhs, name)
+        }
+        ast::ExprVal::In(in_) => expr_uses_var(&in_.lhs, name) || expr_uses_var(&in_.rhs, name),
+        ast::ExprVal::Test(test) => {
+            is_var(&test.ident) || test.args.iter().any(|e| expr_uses_var(e, name))
+        }
+        ast::ExprVal::MacroCall(call) => call.args.values().any(|e| expr_uses_var(e, name)),
+        ast::ExprVal::FunctionCall(call) => call.args.values().any(|e| expr_uses_var(e, name)),
+        ast::ExprVal::Array(exprs) => exprs.iter().any(|e| expr_uses_var(e, name)),
+        ast::ExprVal::StringConcat(concat) => concat.values.iter().any(|v| val_uses_var(v, name)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uses_var() {
+        assert!(uses_var("eslint {{ files }}", "files").unwrap());
+        assert!(uses_var("eslint {{files | replace(from=\"a\", to=\"b\")}}", "files").unwrap());
+        assert!(
+            uses_var(
+                "{% for f in files | split(pat=\" \") %}{{f}}{% endfor %}",
+                "files"
+            )
+            .unwrap()
+        );
+        assert!(uses_var("{% if files %}x{% endif %}", "files").unwrap());
+        assert!(!uses_var("cargo clippy --all-targets", "files").unwrap());
+        assert!(!uses_var("echo files {{workspace}}", "files").unwrap());
+        assert!(!uses_var("{{ files_list }}", "files").unwrap());
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    cmp::Reverse,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use serde_with::{DurationSecondsWithFrac, serde_as};
+
+use crate::{Result, ui::style};
+
+/// Wall-clock time spent in each part of a hook, shown with `--timings`
+#[derive(Debug, Default)]
+pub struct Timings {
+    timings: Mutex<Vec<Timing>>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct Timing {
+    pub kind: TimingKind,
+    pub name: String,
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TimingKind {
+    Hook,
+    GitStatus,
+    Files,
+    Stash,
+    PopStash,
+    Group,
+    Step,
+    Job,
+    /// waiting for other steps to release the files this job reads or writes
+    LockWait,
+    /// waiting for one of the HK_JOBS slots
+    SemaphoreWait,
+}
+
+impl Timings {
+    pub fn record(&self, kind: TimingKind, name: impl Into<String>, start: Instant) {
+        self.timings.lock().unwrap().push(Timing {
+            kind,
+            name: name.into(),
+            duration: start.elapsed(),
+        });
+    }
+
+    /// All of the timings, slowest first
+    pub fn sorted(&self) -> Vec<Timing> {
+        let mut timings = self.timings.lock().unwrap().clone();
+        timings.sort_by_key(|t| Reverse(t.duration));
+        timings
+    }
+
+    pub fn print(&self) {
+        let timings = self.sorted();
+        let kind_width = timings
+            .iter()
+            .map(|t| t.kind.to_string().len())
+            .max()
+            .unwrap_or_default();
+        let name_width = timings
+            .iter()
+            .map(|t| t.name.len())
+            .max()
+            .unwrap_or_default();
+        clx::progress::flush();
+        clx::progress::pause();
+        eprintln!("{}", style::ebold("timings"));
+        for timing in timings {
+            eprintln!(
+                "  {:<kind_width$}  {:<name_width$}  {:>8.3}s",
+                style::edim(timing.kind.to_string()),
+                timing.name,
+                timing.duration.as_secs_f64(),
+            );
+        }
+        clx::progress::resume();
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.sorted())?;
+        xx::file::write(path, json)?;
+        Ok(())
+    }
+}
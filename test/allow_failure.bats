@@ -0,0 +1,26 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "allow_failure" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["lenient"] { check = "echo new linter complaint; exit 3"; allow_failure = true }
+            ["ok"] { check = "echo ok > ok.txt" }
+        }
+    }
+}
+EOF
+    HK_FAIL_FAST=1 run hk check
+    assert_success
+    assert_output --partial "lenient – exit code 3"
+    assert_output --partial "new linter complaint"
+    assert_file_exists ok.txt
+}
@@ -0,0 +1,144 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "cache skips files which passed" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["a"] { glob = "*.txt"; check = "echo checking {{files}}"; cache = true } } }
+}
+EOF
+    echo "test" > a.txt
+    echo "test" > b.txt
+    git add a.txt b.txt
+    run hk check -v
+    assert_success
+    assert_output --partial "DEBUG $ echo checking a.txt b.txt"
+
+    run hk check -v
+    assert_success
+    refute_output --partial "DEBUG $ echo checking"
+
+    echo "changed" > b.txt
+    run hk check -v
+    assert_success
+    assert_output --partial "DEBUG $ echo checking b.txt"
+
+    run hk check -v --no-cache
+    assert_success
+    assert_output --partial "DEBUG $ echo checking a.txt b.txt"
+
+    hk cache clear
+    run hk check -v
+    assert_success
+    assert_output --partial "DEBUG $ echo checking a.txt b.txt"
+}
+
+@test "cache is only used by steps which opt in" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] { glob = "*.txt"; check = "echo a {{files}}" }
+            ["b"] { glob = "*.txt"; check = "echo b"; cache = true }
+        }
+    }
+}
+EOF
+    echo "test" > a.txt
+    git add a.txt
+    hk check
+    run hk check -v
+    assert_success
+    assert_output --partial "DEBUG $ echo a a.txt"
+    assert_output --partial "DEBUG $ echo b"
+}
+
+@test "cache is keyed on the rendered env" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] {
+                glob = "*.txt"
+                check = "echo checking {{files}}"
+                env { ["MODE"] = "{{ get_env(name='MODE', default='') }}" }
+                cache = true
+            }
+        }
+    }
+}
+EOF
+    echo "test" > a.txt
+    git add a.txt
+    MODE=one hk check
+    MODE=one run hk check -v
+    refute_output --partial "DEBUG $ echo checking"
+    MODE=two run hk check -v
+    assert_output --partial "DEBUG $ echo checking a.txt"
+}
+
+@test "cache does not remember failures" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["a"] { glob = "*.txt"; check = "echo checking {{files}}; exit 1"; cache = true } } }
+}
+EOF
+    echo "test" > a.txt
+    git add a.txt
+    run hk check -v
+    assert_failure
+    run hk check -v
+    assert_failure
+    assert_output --partial "DEBUG $ echo checking a.txt; exit 1"
+}
+
+@test "cache info, prune and clear --config" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["a"] { glob = "*.txt"; check = "echo checking {{files}}"; cache = true } } }
+}
+EOF
+    echo "test" > a.txt
+    git add a.txt
+    hk check
+    run hk cache info
+    assert_success
+    assert_output --partial "configs: 1"
+    assert_output --partial "hk.pkl"
+    assert_output --partial "results: 1 steps, 1 files"
+
+    run hk cache prune
+    assert_success
+    run hk cache info
+    assert_output --partial "results: 1 steps, 1 files"
+
+    # using an entry keeps it from being pruned
+    touch -t 202001010000 "$HK_CACHE_DIR"/results/*/*
+    hk check
+    run hk cache prune
+    assert_success
+    run hk cache info
+    assert_output --partial "results: 1 steps, 1 files"
+
+    run hk cache prune --days 0
+    assert_success
+    run hk cache info
+    assert_output --partial "configs: 0"
+    assert_output --partial "results: 0 steps, 0 files"
+
+    hk check
+    hk cache clear --config
+    run hk cache info
+    assert_output --partial "configs: 0"
+    assert_output --partial "results: 1 steps, 1 files"
+}
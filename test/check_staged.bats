@@ -0,0 +1,47 @@
+#!/usr/bin/env bats
+
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+
+teardown() {
+    _common_teardown
+}
+
+@test "check_staged lints the staged version of partially staged files" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        stash = "none"
+        check_staged = true
+        steps {
+            ["no_bad"] { glob = "*.txt"; check = "! grep -H BAD {{files}}" }
+        }
+    }
+}
+EOF
+    echo "good" > a.txt
+    git add hk.pkl a.txt
+    git commit -m "initial commit"
+
+    echo "staged" >> a.txt
+    git add a.txt
+    echo "BAD" >> a.txt
+    run hk run pre-commit
+    assert_success
+    run cat a.txt
+    assert_output "good
+staged
+BAD"
+
+    git checkout a.txt
+    echo "BAD" >> a.txt
+    git add a.txt
+    echo "unstaged" >> a.txt
+    run hk run pre-commit
+    assert_failure
+    assert_output --partial "a.txt:BAD"
+    refute_output --partial "hk-staged"
+}
@@ -0,0 +1,89 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "user and local configs are layered over hk.pkl" {
+    export XDG_CONFIG_HOME="$HOME/.config"
+    mkdir -p "$HOME/.config/hk"
+    cat <<EOF > "$HOME/.config/hk/config.pkl"
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["user"] { check = "echo user step" } } }
+}
+EOF
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] { check = "echo project a" }
+            ["b"] { check = "echo project b" }
+        }
+    }
+}
+EOF
+    cat <<EOF > hk.local.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["a"] { check = "echo local a" } } }
+}
+EOF
+    run hk check -v
+    assert_success
+    assert_output --partial "DEBUG $ echo user step"
+    assert_output --partial "DEBUG $ echo local a"
+    assert_output --partial "DEBUG $ echo project b"
+    refute_output --partial "project a"
+
+    run hk config
+    assert_success
+    assert_output --regexp "# hooks.check.steps.a: [^ ]*/hk.local.pkl"
+    assert_output --regexp "# hooks.check.steps.b: [^ ]*/hk.pkl"
+    assert_output --regexp "# hooks.check.steps.user: [^ ]*/.config/hk/config.pkl"
+}
+
+@test "hook defaults in the local config apply to the project's steps" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["slow"] { check = "sleep 60 && echo done" } } }
+}
+EOF
+    cat <<EOF > hk.local.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { timeout = 1.s }
+}
+EOF
+    run hk check
+    assert_failure
+    assert_output --partial "timed out after 1s"
+}
+
+@test "local config disables a step from the project config" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] { check = "echo project a" }
+            ["b"] { check = "echo project b" }
+        }
+    }
+}
+EOF
+    cat <<EOF > hk.local.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["a"] { enabled = false } } }
+}
+EOF
+    run hk check -v
+    assert_success
+    assert_output --partial "DEBUG $ echo project b"
+    refute_output --partial "project a"
+}
@@ -63,3 +63,76 @@ EOF
     git commit -m "initial commit"
     hk fix -v
 }
+
+@test "depends cycle" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] { check = "echo a"; depends = "b" }
+            ["b"] { check = "echo b"; depends = "a" }
+        }
+    }
+}
+EOF
+    run timeout 10 hk check --all
+    assert_failure
+    assert_output --partial "dependency cycle: a -> b -> a"
+}
+
+@test "depends on earlier group" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["fix"] {
+        steps {
+            ["a"] { fix = "echo ITWORKS > a.txt"; exclusive = true }
+            ["b"] { fix = "cat a.txt > b.txt"; depends = "a" }
+        }
+    }
+}
+EOF
+    git add hk.pkl
+    git commit -m "initial commit"
+    hk fix
+    assert_file_contains b.txt "ITWORKS"
+}
+
+@test "depends on later group" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] { check = "echo a"; depends = "b" }
+            ["b"] { check = "echo b"; exclusive = true }
+        }
+    }
+}
+EOF
+    run hk check --all
+    assert_failure
+    assert_output --partial "a: depends on b which runs in a later group"
+}
+
+@test "depends on missing step" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] { check = "echo checking a"; depends = "missing" }
+        }
+    }
+}
+EOF
+    run hk check --all -v
+    assert_success
+    assert_output --partial "a: depends on missing which is not a step in this hook"
+    assert_output --partial "checking a"
+
+    run hk validate
+    assert_failure
+    assert_output --partial "a: depends on missing which is not a step in this hook"
+}
@@ -0,0 +1,32 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "file_types" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["shell"] { file_types = "shell"; check = "echo shell: {{files}}" }
+            ["python"] { file_types = List("python"); check = "echo python: {{files}}" }
+            ["either"] { glob = "*.txt"; file_types = List("python"); check = "echo either: {{files}}" }
+        }
+    }
+}
+EOF
+    printf '#!/usr/bin/env bash\necho deploy\n' > deploy
+    printf '#!/usr/bin/python3\nprint(1)\n' > manage
+    echo "echo test" > test.sh
+    echo "test" > test.txt
+    git add deploy manage test.sh test.txt
+    run hk check -v
+    assert_success
+    assert_output --partial "DEBUG $ echo shell: deploy test.sh"
+    assert_output --partial "DEBUG $ echo python: manage"
+    assert_output --partial "DEBUG $ echo either: manage test.txt"
+}
@@ -0,0 +1,85 @@
+#!/usr/bin/env bats
+
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+
+teardown() {
+    _common_teardown
+}
+
+@test "intent-to-add files are kept while stashing" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        steps {
+            ["status"] { check = "git status --porcelain" }
+        }
+    }
+}
+EOF
+    seq 1 5 > a.txt
+    git add hk.pkl a.txt
+    git commit -m "initial commit"
+
+    for method in git patch-file; do
+        sed -i.bak '1s/.*/staged/' a.txt && rm a.txt.bak
+        git add a.txt
+        sed -i.bak '5s/.*/unstaged/' a.txt && rm a.txt.bak
+        echo "new" > new.txt
+        git add --intent-to-add new.txt
+
+        HK_STASH=$method run hk run pre-commit
+        assert_success
+        run git status --porcelain
+        assert_line "MM a.txt"
+        assert_line " A new.txt"
+        run cat new.txt
+        assert_output "new"
+        run tail -n 1 a.txt
+        assert_output "unstaged"
+        run git stash list
+        assert_output ""
+
+        git reset --hard
+        rm -f new.txt
+    done
+}
+
+@test "intent-to-add files are kept when every step is skipped" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        steps {
+            ["status"] { check = "git status --porcelain"; profiles = List("slow") }
+        }
+    }
+}
+EOF
+    seq 1 5 > a.txt
+    git add hk.pkl a.txt
+    git commit -m "initial commit"
+
+    for method in git patch-file; do
+        sed -i.bak '1s/.*/staged/' a.txt && rm a.txt.bak
+        git add a.txt
+        sed -i.bak '5s/.*/unstaged/' a.txt && rm a.txt.bak
+        echo "new" > new.txt
+        git add --intent-to-add new.txt
+
+        HK_STASH=$method run hk run pre-commit
+        assert_success
+        assert_output --partial "no steps to run"
+        run git status --porcelain
+        assert_line "MM a.txt"
+        assert_line " A new.txt"
+        run git stash list
+        assert_output ""
+
+        git reset --hard
+        rm -f new.txt
+    done
+}
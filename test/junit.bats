@@ -0,0 +1,60 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "junit" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["pass"] { check = "echo all good" }
+            ["fail"] { check = "echo 'bad <thing>' >&2; exit 1" }
+            ["skip"] { check = "echo skipped"; profiles = List("slow") }
+        }
+    }
+}
+EOF
+    echo "test" > test.js
+    git add test.js
+    HK_FAIL_FAST=0 run hk check --junit junit.xml
+    assert_failure
+    assert_file_contains junit.xml '<testsuite name="check" tests="3" failures="1" errors="0" skipped="1"'
+    assert_file_contains junit.xml '<testcase name="pass" classname="check.pass"'
+    assert_file_contains junit.xml '<system-out>all good'
+    assert_file_contains junit.xml '<failure message="exit code 1">'
+    assert_file_contains junit.xml '<system-err>bad &lt;thing&gt;'
+    assert_file_contains junit.xml '<skipped message="missing profile: slow"/>'
+}
+
+@test "junit is written when there are no files to run" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] { steps { ["a"] { glob = "*.js"; check = "echo checking {{files}}" } } }
+}
+EOF
+    git add hk.pkl
+    git commit -m "initial commit"
+    run hk run pre-commit --junit junit.xml
+    assert_success
+    assert_file_contains junit.xml '<testsuite name="pre-commit" tests="1" failures="0" errors="0" skipped="1"'
+}
+
+@test "reports which can't be written don't change the hook's result" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["fail"] { check = "exit 3" } } }
+}
+EOF
+    touch not_a_dir
+    run hk check --timings-json not_a_dir/timings.json --junit junit.xml
+    assert_failure 3
+    assert_output --partial "failed to write timings"
+    assert_file_contains junit.xml '<failure message="exit code 3">'
+}
@@ -0,0 +1,34 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "pkl server and pkl eval render the same config" {
+    cat <<EOF > linters.pkl
+import "$PKL_PATH/Config.pkl"
+linters = new Mapping<String, Config.Step> {
+    ["a"] { glob = "*.txt"; check = "echo a {{files}}" }
+}
+EOF
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+import "linters.pkl"
+env { ["FOO"] = "bar" }
+hooks {
+    ["check"] { steps = linters.linters }
+}
+EOF
+    run hk -v config
+    assert_success
+    assert_output --partial "with pkl server"
+    assert_output --partial 'check = "echo a {{files}}"'
+    server_output="$(hk config)"
+
+    HK_PKL_SERVER=0 run hk -v config
+    assert_success
+    refute_output --partial "with pkl server"
+    assert_equal "$(HK_PKL_SERVER=0 hk config)" "$server_output"
+}
@@ -0,0 +1,122 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "plan" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        fix = true
+        steps {
+            ["a"] { glob = "*.js"; check_list_files = "echo {{files}}"; fix = "echo fixed > a.txt" }
+            ["b"] { glob = "*.js"; check = "echo checking {{files}}"; fix = "echo fixed > b.txt" }
+            ["c"] { check = "echo checking"; profiles = List("slow") }
+            ["d"] { check = "echo checking"; condition = "false" }
+            ["e"] { glob = "*.txt"; check = "echo checking {{files}}" }
+        }
+    }
+}
+EOF
+    echo "test" > test.js
+    git add test.js
+    run hk run pre-commit --plan
+    assert_success
+    assert_output --partial "pre-commit – fix – stash: none – 1 file"
+    assert_output --partial "files in contention: test.js"
+    assert_output --partial "a – check_list_files first, then fix – 1 file"
+    assert_output --partial "c – skipped: missing profile: slow"
+    assert_output --partial "d – skipped: condition: false"
+    assert_output --partial "e – skipped: no file matches"
+    assert_file_not_exists a.txt
+    assert_file_not_exists b.txt
+}
+
+@test "plan HK_SKIP_STEPS" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["a"] { check = "echo checking {{files}}" } } }
+}
+EOF
+    echo "test" > test.js
+    HK_SKIP_STEPS=a run hk check --plan test.js
+    assert_success
+    assert_output --partial "a – skipped: HK_SKIP_STEPS"
+}
+
+@test "plan json" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] { check = "echo checking {{files}}" }
+            ["b"] { check = "echo checking"; profiles = List("slow") }
+        }
+    }
+}
+EOF
+    echo "test" > test.js
+    run hk check --plan --json test.js
+    assert_success
+    assert_output --partial '"command": "echo checking test.js"'
+    assert_output --partial '"reason": "missing_profile"'
+}
+
+@test "plan with no files to run" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] { steps { ["a"] { glob = "*.js"; check = "echo checking {{files}}" } } }
+}
+EOF
+    git add hk.pkl
+    git commit -m "init"
+    run hk run pre-commit --plan
+    assert_success
+    assert_output --partial "no files to run"
+    refute_output --partial "pre-commit –"
+}
+
+@test "plan json with no files to run" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] { steps { ["a"] { glob = "*.js"; check = "echo checking {{files}}" } } }
+}
+EOF
+    git add hk.pkl
+    git commit -m "init"
+    run hk run pre-commit --plan --json
+    assert_success
+    refute_output --partial "no files to run"
+    assert_output --partial '"files": []'
+    assert_output --partial '"name": "a"'
+    assert_output --partial '"reason": "no_files"'
+}
+
+@test "plan files in contention by file_types" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        fix = true
+        steps {
+            ["a"] { file_types = "javascript"; check = "echo checking {{files}}"; fix = "echo fixed > a.txt" }
+            ["b"] { file_types = "javascript"; check = "echo checking {{files}}"; fix = "echo fixed > b.txt" }
+        }
+    }
+}
+EOF
+    echo "test" > test.js
+    git add test.js
+    run hk run pre-commit --plan
+    assert_success
+    assert_output --partial "files in contention: test.js"
+    assert_output --partial "a – check first, then fix – 1 file"
+}
@@ -0,0 +1,73 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "profiles enabled by condition imply other profiles" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+profiles {
+    ["ci"] {
+        description = "running in CI"
+        condition = "env.CI != \"\""
+        implies = List("slow")
+    }
+}
+hooks {
+    ["check"] {
+        steps {
+            ["slow_step"] { profiles = List("slow"); check = "echo slow step" }
+        }
+    }
+}
+EOF
+    CI= run hk check -v
+    assert_success
+    refute_output --partial "DEBUG $ echo slow step"
+
+    CI=1 run hk check -v
+    assert_success
+    assert_output --partial "DEBUG $ echo slow step"
+
+    CI=1 run hk profiles
+    assert_success
+    assert_output --regexp "ci +enabled +running in CI"
+    assert_output --regexp "slow +enabled"
+}
+
+@test "unknown profiles are an error" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+profiles {
+    ["fast"] { default = true }
+}
+EOF
+    run hk check --profile nope
+    assert_failure
+    assert_output --partial "unknown profile: nope"
+}
+
+@test "--profile only overrides the profiles it names" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+settings {
+    profiles = List("!slow")
+}
+profiles {
+    ["slow"] { default = true }
+    ["fast"] {}
+}
+EOF
+    run hk --profile fast profiles
+    assert_success
+    assert_output --regexp "slow +disabled"
+    assert_output --regexp "fast +enabled"
+
+    HK_PROFILE=slow run hk --profile '!fast' profiles
+    assert_success
+    assert_output --regexp "slow +enabled"
+    assert_output --regexp "fast +disabled"
+}
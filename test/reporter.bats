@@ -0,0 +1,61 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "reporter github" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["lint"] {
+                check = "echo 'test.sh:3:5: missing quote' >&2; exit 1"
+                output_parser = "regex"
+            }
+            ["plain"] { check = "echo fine" }
+        }
+    }
+}
+EOF
+    echo "test" > test.sh
+    git add test.sh
+    HK_FAIL_FAST=0 run hk check --reporter github
+    assert_failure
+    assert_output --partial "::group::lint – failed: exit code 1"
+    assert_output --partial "::group::plain – passed"
+    assert_output --partial "::endgroup::"
+    assert_output --partial "::error file=test.sh,line=3,col=5,title=lint::missing quote"
+}
+
+@test "reporter github groups a step's jobs" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["lint"] {
+                dir = "sub"
+                batch = true
+                check = #"for f in {{files}}; do echo "\$f:1:1: bad" >&2; done; case "{{files}}" in *b.sh*) sleep 1; exit 1;; esac"#
+                output_parser = "regex"
+            }
+        }
+    }
+}
+EOF
+    mkdir sub
+    echo "test" > sub/a.sh
+    echo "test" > sub/b.sh
+    git add sub
+    HK_JOBS=2 run hk check --reporter github
+    assert_failure
+    assert_equal "$(grep -c '::group::' <<< "$output")" 1
+    assert_output --partial "::group::lint – failed: exit code 1"
+    assert_output --partial "lint [1/2] – passed"
+    assert_output --partial "::error file=sub/a.sh,line=1,col=1,title=lint::bad"
+    assert_output --partial "::error file=sub/b.sh,line=1,col=1,title=lint::bad"
+}
@@ -0,0 +1,48 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "retries" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["flaky"] {
+                check = "if [ -f attempted ]; then echo ok; else touch attempted; echo flaky failure; exit 1; fi"
+                retries = 1
+            }
+        }
+    }
+}
+EOF
+    run hk check
+    assert_success
+    assert_output --partial "flaky: attempt 1/2 failed"
+    assert_output --partial "flaky failure"
+}
+
+@test "retries exhausted" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["broken"] {
+                check = "echo attempt >> attempts.txt; exit 1"
+                retries = 2
+                retry_delay = 100.ms
+            }
+        }
+    }
+}
+EOF
+    run hk check
+    assert_failure
+    assert_output --partial "broken: attempt 2/3 failed"
+    assert_equal "$(wc -l < attempts.txt | tr -d ' ')" "3"
+}
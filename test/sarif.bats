@@ -0,0 +1,74 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "sarif" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["lint"] {
+                check = "echo 'test.sh:3:5: warning: missing quote' >&2; exit 1"
+                output_parser = "regex"
+            }
+            ["shellcheck"] {
+                check = #"echo '[{"file":"test.sh","line":2,"column":6,"level":"error","code":2086,"message":"Double quote"}]'"#
+                output_parser = "shellcheck-json"
+            }
+            ["plain"] { check = "echo fine" }
+        }
+    }
+}
+EOF
+    echo "test" > test.sh
+    git add test.sh
+    HK_FAIL_FAST=0 run hk check --sarif hk.sarif
+    assert_failure
+    assert_file_contains hk.sarif '"name": "lint"'
+    assert_file_contains hk.sarif '"name": "shellcheck"'
+    assert_file_contains hk.sarif '"level": "warning"'
+    assert_file_contains hk.sarif '"text": "missing quote"'
+    assert_file_contains hk.sarif '"startLine": 3'
+    assert_file_contains hk.sarif '"ruleId": "SC2086"'
+    assert_file_not_contains hk.sarif '"name": "plain"'
+}
+
+@test "sarif invalid output_pattern" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] { steps { ["lint"] { check = "echo"; output_pattern = "(?P<file>.+)" } } }
+}
+EOF
+    run hk validate
+    assert_failure
+    assert_output --partial "lint: output_pattern has no message group"
+}
+
+@test "sarif paths from steps with a dir" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["lint"] {
+                dir = "sub"
+                check = "echo 'test.sh:3:5: missing quote' >&2; exit 1"
+                output_parser = "regex"
+            }
+        }
+    }
+}
+EOF
+    mkdir sub
+    echo "test" > sub/test.sh
+    git add sub/test.sh
+    run hk check --sarif hk.sarif
+    assert_failure
+    assert_file_contains hk.sarif '"uri": "sub/test.sh"'
+}
@@ -0,0 +1,43 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "settings block sets defaults which local config and env override" {
+    unset HK_JOBS
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+settings {
+    jobs = 3
+    fail_fast = false
+    check_first = false
+    profiles = List("slow")
+}
+EOF
+    cat <<EOF > hk.local.pkl
+amends "$PKL_PATH/Config.pkl"
+settings {
+    jobs = 2
+    stash = "patch-file"
+}
+EOF
+    run hk config --settings
+    assert_success
+    assert_output --partial "jobs = 2"
+    assert_output --partial "fail_fast = false"
+    assert_output --partial "check_first = false"
+    assert_output --partial 'stash = "patch-file"'
+    assert_output --partial 'enabled_profiles = ["slow"]'
+
+    HK_JOBS=5 HK_FAIL_FAST=1 run hk config --settings
+    assert_success
+    assert_output --partial "jobs = 5"
+    assert_output --partial "fail_fast = true"
+
+    run hk --jobs 7 config --settings
+    assert_success
+    assert_output --partial "jobs = 7"
+}
@@ -0,0 +1,75 @@
+#!/usr/bin/env bats
+
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+
+teardown() {
+    _common_teardown
+}
+
+_setup_partially_staged() {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        fix = true
+        stash = "none"
+        steps {
+            ["fmt"] {
+                glob = "*.txt"
+                stage = "*.txt"
+                check = "! grep -q bad {{files}}"
+                fix = "sed -i.bak s/bad/good/ {{files}} && rm -f *.bak"
+            }
+        }
+    }
+}
+EOF
+    seq 1 10 > a.txt
+    git add hk.pkl a.txt
+    git commit -m "initial commit"
+
+    sed -i.bak '2s/.*/bad/' a.txt && rm a.txt.bak
+    git add a.txt
+}
+
+_assert_fixes_staged() {
+    sed -i.bak '9s/.*/unstaged/' a.txt && rm a.txt.bak
+    run hk run pre-commit
+    assert_success
+
+    run git show :a.txt
+    assert_line --index 1 "good"
+    assert_line --index 8 "9"
+    run cat a.txt
+    assert_line --index 1 "good"
+    assert_line --index 8 "unstaged"
+}
+
+@test "fixes to partially staged files are staged without their unstaged changes" {
+    _setup_partially_staged
+    _assert_fixes_staged
+}
+
+@test "fixes to partially staged files are staged without their unstaged changes without libgit2" {
+    export HK_LIBGIT2=0
+    _setup_partially_staged
+    _assert_fixes_staged
+}
+
+@test "fixes which overlap unstaged changes are not staged" {
+    _setup_partially_staged
+    sed -i.bak '9s/.*/bad unstaged/' a.txt && rm a.txt.bak
+    git show :a.txt > "$TEST_TEMP_DIR/index"
+    run hk run pre-commit
+    assert_success
+    assert_output --partial "fmt: fixes to a.txt overlap its unstaged changes so they were not staged"
+
+    run git show :a.txt
+    assert_output "$(cat "$TEST_TEMP_DIR/index")"
+    run cat a.txt
+    assert_line --index 1 "good"
+    assert_line --index 8 "good unstaged"
+}
@@ -0,0 +1,120 @@
+#!/usr/bin/env bats
+
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+
+teardown() {
+    _common_teardown
+}
+
+@test "changes stashed by a killed hk run can be restored" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        stash = "patch-file"
+        steps {
+            ["die"] { check = "kill -9 \$PPID" }
+        }
+    }
+    ["check"] {
+        steps {
+            ["ok"] { check = "exit 0" }
+        }
+    }
+}
+EOF
+    seq 1 10 > a.txt
+    git add hk.pkl a.txt
+    git commit -m "initial commit"
+
+    sed -i.bak '1s/.*/staged/' a.txt && rm a.txt.bak
+    git add a.txt
+    sed -i.bak '10s/.*/unstaged/' a.txt && rm a.txt.bak
+    run hk run pre-commit
+    assert_failure
+    refute_output --partial "unstaged"
+    run tail -n 1 a.txt
+    assert_output "10"
+
+    run hk stash list
+    assert_success
+    assert_output --partial "patch-file"
+
+    run hk check
+    assert_output --partial "stash was left by an interrupted hk run"
+
+    run hk stash restore
+    assert_success
+    run tail -n 1 a.txt
+    assert_output "unstaged"
+    run git show :a.txt
+    assert_line --index 0 "staged"
+    run hk stash list
+    refute_output --partial "patch-file"
+}
+
+@test "intent-to-add files removed by a killed hk run can be restored" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        stash = "git"
+        steps {
+            ["die"] { check = "kill -9 \$PPID" }
+        }
+    }
+}
+EOF
+    seq 1 10 > a.txt
+    git add hk.pkl a.txt
+    git commit -m "initial commit"
+
+    sed -i.bak '1s/.*/staged/' a.txt && rm a.txt.bak
+    git add a.txt
+    echo "new" > new.txt
+    git add --intent-to-add new.txt
+    run hk run pre-commit
+    assert_failure
+    run git status --porcelain
+    assert_line "?? new.txt"
+
+    run hk stash list
+    assert_success
+    assert_output --partial "intent-to-add: new.txt"
+
+    run hk stash restore
+    assert_success
+    run git status --porcelain
+    assert_line " A new.txt"
+}
+
+@test "finished hk runs leave no stash journal" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        stash = "git"
+        steps {
+            ["ok"] { check = "exit 0" }
+        }
+    }
+}
+EOF
+    seq 1 10 > a.txt
+    git add hk.pkl a.txt
+    git commit -m "initial commit"
+
+    sed -i.bak '1s/.*/staged/' a.txt && rm a.txt.bak
+    git add a.txt
+    echo "new" > new.txt
+    git add --intent-to-add new.txt
+    run hk run pre-commit
+    assert_success
+    run git status --porcelain
+    assert_line " A new.txt"
+    run hk stash list
+    assert_output --partial "no stashed changes"
+}
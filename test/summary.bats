@@ -0,0 +1,34 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "summary of failed steps" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] { check = "echo a failed; exit 2" }
+            ["b"] { check = "echo b failed >&2; exit 1" }
+            ["c"] { check = "echo c passed" }
+        }
+    }
+}
+EOF
+    export HK_STATE_DIR="$TEST_TEMP_DIR/state"
+    HK_FAIL_FAST=0 run hk check
+    assert_failure
+    assert_output --partial "a – exit code 2"
+    assert_output --partial "a failed"
+    assert_output --partial "b – exit code 1"
+    assert_output --partial "b failed"
+    refute_output --partial "c – exit code"
+    refute_output --partial "Error running"
+    assert_file_contains "$HK_STATE_DIR/output/a.log" "a failed"
+    assert_file_contains "$HK_STATE_DIR/output/b.log" "b failed"
+    assert_file_not_exists "$HK_STATE_DIR/output/c.log"
+}
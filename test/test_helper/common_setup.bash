@@ -17,6 +17,7 @@ _common_setup() {
     export GIT_CONFIG_NOSYSTEM=1
     export HK_JOBS=2
     export HOME="$TEST_TEMP_DIR"
+    export HK_CACHE_DIR="$TEST_TEMP_DIR/.cache/hk"
     git config --global init.defaultBranch main
     git config --global user.email "test@example.com"
     git config --global user.name "Test User"
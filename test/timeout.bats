@@ -0,0 +1,81 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "timeout" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["slow"] { check = "sleep 60 && echo done"; timeout = 1.s }
+        }
+    }
+}
+EOF
+    SECONDS=0
+    run hk check
+    assert_failure
+    assert_output --partial "timed out after 1s"
+    assert [ "$SECONDS" -lt 30 ]
+}
+
+@test "timeout warn" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["slow"] { check = "sleep 60 && echo done"; timeout = 500.ms; on_timeout = "warn" }
+            ["fast"] { check = "echo fast > fast.txt" }
+        }
+    }
+}
+EOF
+    run hk check
+    assert_success
+    assert_output --partial "timed out after 0.5s"
+    assert_file_exists fast.txt
+}
+
+@test "timeout hook default" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        timeout = 1.s
+        steps {
+            ["slow"] { check = "sleep 60 && echo done" }
+            ["slower"] { check = "sleep 60 && echo done"; timeout = 2.s }
+        }
+    }
+}
+EOF
+    run hk check
+    assert_failure
+    assert_output --partial "timed out after 1s"
+}
+
+@test "timeout kills processes started in the background" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["slow"] { check = "sleep 100 & echo \$! > bg.pid; sleep 60"; timeout = 1.s }
+        }
+    }
+}
+EOF
+    run hk check
+    assert_failure
+    assert_output --partial "timed out after 1s"
+    sleep 0.5
+    # killed processes which haven't been reaped yet show up as zombies
+    run ps -o stat= -p "$(cat bg.pid)"
+    refute_output --regexp "^[^Z]"
+}
@@ -0,0 +1,52 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "timings" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["a"] { check = "sleep 0.1" }
+            ["b"] { check = "echo b" }
+        }
+    }
+}
+EOF
+    echo "test" > test.js
+    git add test.js
+    run hk check --timings --timings-json timings.json
+    assert_success
+    assert_output --partial "timings"
+    assert_output --partial "git_status"
+    assert_output --partial "lock_wait"
+    assert_line --regexp "step +a +[0-9.]+s"
+    assert_file_contains timings.json '"kind": "hook"'
+    assert_file_contains timings.json '"name": "check"'
+}
+
+@test "timings are written when there is nothing to run" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] { steps { ["a"] { glob = "*.js"; check = "echo checking {{files}}" } } }
+    ["check"] { steps { ["b"] { check = "echo b"; profiles = List("slow") } } }
+}
+EOF
+    git add hk.pkl
+    git commit -m "initial commit"
+    run hk run pre-commit --timings-json timings.json
+    assert_success
+    assert_output --partial "no files to run"
+    assert_file_contains timings.json '"name": "pre-commit"'
+
+    run hk check --all --timings-json timings.json
+    assert_success
+    assert_output --partial "no steps to run"
+    assert_file_contains timings.json '"name": "check"'
+}
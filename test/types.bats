@@ -0,0 +1,49 @@
+setup() {
+    load 'test_helper/common_setup'
+    _common_setup
+}
+teardown() {
+    _common_teardown
+}
+
+@test "types" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["check"] {
+        steps {
+            ["text"] { glob = "test*"; types = "text"; check = "echo text: {{files}}" }
+            ["binary"] { glob = "test*"; types = "binary"; check = "echo binary: {{files}}" }
+            ["executable"] { glob = "test*"; types = List("text", "executable"); check = "echo executable: {{files}}" }
+        }
+    }
+}
+EOF
+    echo "test" > test.txt
+    printf 'a\0b' > test.bin
+    printf '#!/bin/sh\n' > test.sh
+    chmod +x test.sh
+    git add test.txt test.bin test.sh
+    run hk check -v
+    assert_success
+    assert_output --partial "DEBUG $ echo text: test.sh test.txt"
+    assert_output --partial "DEBUG $ echo binary: test.bin"
+    assert_output --partial "DEBUG $ echo executable: test.sh"
+}
+
+@test "types newlines skips binary files" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+import "$PKL_PATH/Builtins.pkl"
+hooks { ["fix"] { fix = true; steps { ["newlines"] = Builtins.newlines } } }
+EOF
+    printf 'test' > test.txt
+    printf 'a\0b' > test.bin
+    cp test.bin expected.bin
+    git add test.txt test.bin
+    run hk fix -v
+    assert_success
+    refute_output --partial "test.bin"
+    assert_equal "$(tail -c1 test.txt | od -An -c | tr -d ' ')" '\n'
+    cmp test.bin expected.bin
+}
@@ -19,3 +19,26 @@ hooks {
 EOF
     hk validate
 }
+
+@test "validate errors" {
+    cat <<EOF > hk.pkl
+amends "$PKL_PATH/Config.pkl"
+hooks {
+    ["pre-commit"] {
+        steps {
+            ["a"] { glob = "{a"; check = "echo {{files"; depends = List("b", "missing") }
+            ["b"] { check = "echo b"; depends = "a"; condition = "1 +" }
+            ["c"] { glob = "*.js" }
+        }
+    }
+}
+EOF
+    run hk validate
+    assert_failure
+    assert_output --partial "pre-commit: a: invalid glob"
+    assert_output --partial "pre-commit: a: invalid template in check"
+    assert_output --partial "pre-commit: a: depends on missing which is not a step in this hook"
+    assert_output --partial "pre-commit: b: invalid condition"
+    assert_output --partial "pre-commit: c: no check or fix command"
+    assert_output --partial "pre-commit: dependency cycle: a -> b -> a"
+}